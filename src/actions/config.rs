@@ -0,0 +1,86 @@
+use crate::core::config::{self, Config, ConfigEntry};
+use miette::Report;
+use miette::Result as R;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Parameters {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: String,
+        /// Write to the project config instead of the user config.
+        #[serde(default)]
+        project: bool,
+    },
+    List,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub entries: Vec<ConfigEntry>,
+    /// The file a `set` wrote to.
+    pub written: Option<String>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConfigError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters, config: &Config) -> R<Result, Error> {
+    match params {
+        Parameters::Get { key } => Ok(Result {
+            entries: vec![config.get(key).map_err(|e| Error { base: e.into() })?],
+            written: None,
+        }),
+
+        Parameters::Set {
+            key,
+            value,
+            project,
+        } => {
+            let path = if *project {
+                config.project_path()
+            } else {
+                config.user_path()
+            }
+            .ok_or_else(|| Error {
+                base: Report::msg("Unable to determine the config file location"),
+            })?;
+
+            config::set_value(path, key, value).map_err(|e| Error { base: e.into() })?;
+
+            // Show the effective value afterwards, which may still come from a higher layer.
+            let reloaded = config.reload().map_err(|e| Error { base: e.into() })?;
+            Ok(Result {
+                entries: vec![reloaded.get(key).map_err(|e| Error { base: e.into() })?],
+                written: Some(path.display().to_string()),
+            })
+        }
+
+        Parameters::List => Ok(Result {
+            entries: config.entries(),
+            written: None,
+        }),
+    }
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,159 @@
+use crate::actions::install;
+use crate::core::dependencies::{
+    self, Environment, PackageRequirement, PackageResolver, ResolveLimits, Strategy,
+};
+use crate::core::installed::InstalledPackages;
+use crate::core::repository::Repository;
+use crate::core::target::Target;
+use crate::core::version::parse_version;
+use miette::Report;
+use miette::Result as R;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_path: String,
+    pub repository_url: String,
+    pub package: String,
+    /// Fail on invalid releases in the package index instead of skipping them.
+    #[serde(default)]
+    pub strict: bool,
+    /// Give up resolving a release after this many backtracking steps.
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+    /// Give up resolving a release after this many seconds.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Platform to pick platform-specific dependencies for, instead of the current one.
+    #[serde(default)]
+    pub target: Option<Target>,
+    /// SuperCollider version releases must work with. Not checked if unset.
+    #[serde(default)]
+    pub supercollider: Option<Version>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReleaseRow {
+    pub version: String,
+    pub url: String,
+    pub dependencies: BTreeMap<String, String>,
+    pub installed: bool,
+    /// False if the release's dependencies cannot be satisfied from the repository. Yanked
+    /// releases count, as a lockfile can still install them.
+    pub installable: bool,
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub yanked_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub name: String,
+    pub description: String,
+    pub authors: Vec<String>,
+    pub license: String,
+    pub url: String,
+    pub repo: String,
+    pub installed_version: Option<String>,
+    /// Newest first.
+    pub releases: Vec<ReleaseRow>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InfoError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let package = repo.get_package(&params.package).ok_or_else(|| Error {
+        base: Report::msg(format!("Package {} not found", params.package)),
+    })?;
+    let installed = InstalledPackages::load(Path::new(&params.repository_path))
+        .map_err(|e| Error { base: e.into() })?;
+    let installed_version = installed.get(&package.name).map(|p| p.version.clone());
+    let mut index =
+        dependencies::Repository::new(repo, params.strict).map_err(|e| Error { base: e.into() })?;
+    index.yanked.clear();
+    let environment = Environment {
+        target: params.target.clone().unwrap_or_default(),
+        supercollider: params.supercollider.clone(),
+    };
+    let limits = install::limits(params.max_steps, params.timeout_seconds);
+
+    let mut releases = package
+        .releases
+        .iter()
+        .map(|release| {
+            let version = parse_version(&release.version).ok();
+            let installable = version
+                .as_ref()
+                .is_some_and(|v| is_installable(&index, &package.name, v, &environment, limits));
+            (
+                version.clone(),
+                ReleaseRow {
+                    version: release.version.to_string(),
+                    url: release.url.clone(),
+                    dependencies: release
+                        .dependencies
+                        .iter()
+                        .map(|(name, req)| (name.to_string(), req.to_string()))
+                        .collect(),
+                    installed: version.is_some() && version == installed_version,
+                    installable,
+                    yanked: release.yanked,
+                    yanked_reason: release.yanked_reason.clone(),
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    releases.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Ok(Result {
+        name: package.name.clone(),
+        description: package.description.clone(),
+        authors: package.authors.clone(),
+        license: package.license.clone(),
+        url: package.url.clone(),
+        repo: package.repo.clone(),
+        installed_version: installed_version.map(|v| v.to_string()),
+        releases: releases.into_iter().map(|(_, row)| row).collect(),
+    })
+}
+
+/// Whether the full dependency tree of exactly this release can be resolved for
+/// `environment`. A search that goes over `limits` counts as not installable.
+fn is_installable(
+    index: &dependencies::Repository,
+    name: &str,
+    version: &Version,
+    environment: &Environment,
+    limits: ResolveLimits,
+) -> bool {
+    let Ok(requirement) = PackageRequirement::new(name.to_string(), format!("={}", version)) else {
+        return false;
+    };
+    let mut resolver = PackageResolver::new(vec![requirement], index.clone(), Strategy::latest())
+        .environment(environment)
+        .limits(limits);
+    resolver.resolve().is_ok()
+}
+//////////////////////////////////////////////////////////////////////////////
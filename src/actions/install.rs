@@ -0,0 +1,850 @@
+use crate::core::dependencies::{
+    self, dependency_condition, split_features, Environment, Locking, PackageRequirement,
+    PackageResolver, PackageVersion, Resolution, ResolveLimits, Strategy,
+};
+use crate::core::download;
+use crate::core::installed::{package_path, InstalledPackage, InstalledPackages};
+use crate::core::lockfile::{self, LockError, LockedPackage, Lockfile};
+use crate::core::manifest;
+use crate::core::repository::{find_release, Repository};
+use crate::core::settings::PackageOverride;
+use crate::core::target::Target;
+use crate::core::version::parse_version;
+use crate::specs::manifest::Manifest;
+use crate::specs::Release;
+use miette::Report;
+use miette::Result as R;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::Path;
+
+/// A package to install, with an optional version requirement (any version if omitted).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageSpec {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+impl PackageSpec {
+    /// Parse `name` or `name@requirement`, e.g. `package1@^1.2`. The name may ask for features,
+    /// as in `package1[gui]@^1.2`.
+    pub fn parse(spec: &str) -> Self {
+        match spec.split_once('@') {
+            Some((name, version)) => Self {
+                name: name.trim().to_string(),
+                version: Some(version.trim().to_string()),
+            },
+            None => Self {
+                name: spec.trim().to_string(),
+                version: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_path: String,
+    pub repository_url: String,
+    pub packages: Vec<PackageSpec>,
+    /// Lockfile to prefer versions from and to record the result in. It only records what
+    /// the project manifest needs, or without a manifest, what `packages` need.
+    #[serde(default)]
+    pub lockfile: Option<String>,
+    /// Fail instead of changing the lockfile.
+    #[serde(default)]
+    pub locked: bool,
+    /// Project manifest to install from when `packages` is empty, which defaults to the one in
+    /// the current directory then.
+    #[serde(default)]
+    pub manifest: Option<String>,
+    /// Leave out the manifest's dev-dependencies.
+    #[serde(default)]
+    pub no_dev: bool,
+    /// Which compatible versions to pick. Locked versions are only preferred by `highest` and
+    /// `minimal-change`, so that `lowest` and `lowest-direct` really test lower bounds.
+    #[serde(default)]
+    pub resolution: Resolution,
+    /// Give up resolving after this many backtracking steps.
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+    /// Give up resolving after this many seconds.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Fail on invalid releases in the package index instead of skipping them.
+    #[serde(default)]
+    pub strict: bool,
+    /// Platform to pick platform-specific dependencies for, instead of the current one.
+    #[serde(default)]
+    pub target: Option<Target>,
+    /// SuperCollider version releases must work with. Not checked if unset.
+    #[serde(default)]
+    pub supercollider: Option<Version>,
+    /// Packages to take from a local directory, a git repository or a forced version instead
+    /// of the index, by package name.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, PackageOverride>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstalledRow {
+    pub name: String,
+    pub version: String,
+    pub previous_version: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub installed: Vec<InstalledRow>,
+    pub unchanged: Vec<String>,
+    /// E.g. yanked releases that were installed because the lockfile pins them.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "InstallError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub async fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let repository_path = Path::new(&params.repository_path);
+    let mut installed =
+        InstalledPackages::load(repository_path).map_err(|e| Error { base: e.into() })?;
+
+    let lock_path = params.lockfile.as_deref().map(Path::new);
+    let lock = match lock_path {
+        Some(path) => Lockfile::load(path).map_err(|e| Error { base: e.into() })?,
+        None => None,
+    };
+
+    let manifest_path = match (&params.manifest, params.packages.is_empty()) {
+        (Some(path), _) => Some(Path::new(path)),
+        (None, true) => Some(Path::new(manifest::MANIFEST_FILE)),
+        (None, false) => None,
+    };
+    let project = match manifest_path {
+        Some(path) => manifest::load(path).map_err(|e| Error { base: e.into() })?,
+        None => None,
+    };
+    let packages = if params.packages.is_empty() {
+        manifest_packages(project.as_ref(), manifest_path, params.no_dev)?
+    } else {
+        params.packages.clone()
+    };
+    // The lockfile records what the project needs, not everything installed alongside it.
+    let roots = match &project {
+        Some(project) => manifest::requirements(project, !params.no_dev)
+            .into_keys()
+            .collect::<Vec<_>>(),
+        None => packages.iter().map(|spec| spec.name.clone()).collect(),
+    };
+    let mut requested = requested_packages(&installed, &packages);
+    let strategy = strategy(params.resolution, &installed, lock.as_ref());
+    let limits = limits(params.max_steps, params.timeout_seconds);
+    let index = apply_overrides(
+        index(repo, params.strict)?,
+        &mut requested,
+        &params.overrides,
+    )?;
+    let environment = Environment {
+        target: params.target.clone().unwrap_or_default(),
+        supercollider: params.supercollider.clone(),
+    };
+    let lock_part =
+        |selected: &[PackageVersion]| needed_by(&roots, selected, repo, &environment.target);
+    let prefer_lock = matches!(
+        params.resolution,
+        Resolution::Highest | Resolution::MinimalChange
+    );
+    let selected = match (&lock, lock_path) {
+        (Some(lock), Some(path)) if params.locked => resolve_frozen(
+            &requested,
+            &index,
+            &strategy,
+            limits,
+            &environment,
+            lock,
+            path,
+            &lock_part,
+        )?,
+        (Some(lock), _) if prefer_lock => resolve_locked(
+            &requested,
+            &index,
+            &strategy,
+            limits,
+            &environment,
+            lock,
+            Locking::Prefer,
+        )?,
+        (None, _) if params.locked => {
+            return Err(Error {
+                base: Report::msg(format!(
+                    "--locked needs an existing lockfile, but there is none at {}",
+                    params.lockfile.as_deref().unwrap_or(lockfile::LOCK_FILE)
+                )),
+            });
+        }
+        _ => resolve(&requested, &index, &strategy, limits, &environment)?,
+    };
+
+    let mut result = Result {
+        installed: Vec::new(),
+        unchanged: Vec::new(),
+        warnings: yank_warnings(&selected, &index),
+    };
+    result
+        .warnings
+        .extend(override_warnings(&selected, &params.overrides));
+
+    for package_version in selected.iter() {
+        let name = package_version.name.clone();
+        let requirement = requested_for(&requested, &name);
+        let source = params.overrides.get(&name).filter(|o| o.has_source());
+
+        if let Some(existing) = installed.packages.get_mut(&name) {
+            // Sources that are overridden may have changed, so they are always installed again.
+            if existing.version == package_version.version && source.is_none() {
+                if let Some((features, _)) = &requirement {
+                    existing.features = features.clone();
+                }
+                existing.requirement = requirement
+                    .map(|(_, version)| version)
+                    .or(existing.requirement.take());
+                result.unchanged.push(name);
+                continue;
+            }
+        }
+
+        let checksum = lock
+            .as_ref()
+            .and_then(|lock| lock.get(&name))
+            .filter(|locked| locked.version == package_version.version)
+            .and_then(|locked| locked.checksum.clone());
+        let previous = install_package(
+            package_version,
+            requirement,
+            checksum,
+            source,
+            &environment.target,
+            repo,
+            &mut installed,
+            repository_path,
+        )
+        .await?;
+        let url = installed
+            .get(&name)
+            .map(|p| p.url.clone())
+            .unwrap_or_default();
+
+        result.installed.push(InstalledRow {
+            name,
+            version: index.display_version(&package_version.name, &package_version.version),
+            previous_version: previous.map(|p| p.version.to_string()),
+            url,
+        });
+    }
+
+    installed
+        .save(repository_path)
+        .map_err(|e| Error { base: e.into() })?;
+    if let Some(path) = lock_path {
+        lock_selection(
+            &lock_part(&selected),
+            repo,
+            &installed,
+            lock.as_ref(),
+            &params.overrides,
+            &environment.target,
+        )
+        .save(path)
+        .map_err(|e| Error { base: e.into() })?;
+    }
+
+    Ok(result)
+}
+
+/// A warning for every yanked release in `selected`.
+fn yank_warnings(selected: &[PackageVersion], index: &dependencies::Repository) -> Vec<String> {
+    selected
+        .iter()
+        .filter(|pv| index.is_yanked(&pv.name, &pv.version))
+        .map(|pv| {
+            let version = index.display_version(&pv.name, &pv.version);
+            let warning = match index.yank_reason(&pv.name, &pv.version) {
+                Some(reason) => format!("{} {} is yanked: {}", pv.name, version, reason),
+                None => format!("{} {} is yanked", pv.name, version),
+            };
+            tracing::warn!("{}", warning);
+            warning
+        })
+        .collect()
+}
+
+/// A warning for every package in `selected` that is taken from an override.
+pub fn override_warnings(
+    selected: &[PackageVersion],
+    overrides: &BTreeMap<String, PackageOverride>,
+) -> Vec<String> {
+    selected
+        .iter()
+        .filter_map(|pv| overrides.get(&pv.name).map(|o| (&pv.name, o)))
+        .map(|(name, entry)| {
+            let warning = format!("{} is overridden: {}", name, entry);
+            tracing::warn!("{}", warning);
+            warning
+        })
+        .collect()
+}
+
+/// Apply `overrides` to `index` before resolving: packages with a forced version only offer
+/// that version, and the requests for them in `requested` ask for exactly that version.
+pub fn apply_overrides(
+    index: dependencies::Repository,
+    requested: &mut BTreeMap<String, String>,
+    overrides: &BTreeMap<String, PackageOverride>,
+) -> R<dependencies::Repository, Error> {
+    let mut forced = HashMap::new();
+    for (name, entry) in overrides.iter() {
+        match &entry.version {
+            Some(version) => {
+                let version = parse_version(version).map_err(|e| Error {
+                    base: Report::msg(format!(
+                        "Invalid version {} in the override for {}: {}",
+                        version, name, e
+                    )),
+                })?;
+                // Without a source to install from, the version has to come from the index.
+                let indexed = index
+                    .get_versions(name)
+                    .iter()
+                    .any(|pv| pv.version == version);
+                if !entry.has_source() && !indexed {
+                    return Err(Error {
+                        base: Report::msg(format!(
+                            "The override for {} asks for version {}, which is not in the package index",
+                            name, version
+                        )),
+                    });
+                }
+                forced.insert(name.clone(), version);
+            }
+            None if index.get_versions(name).is_empty() => {
+                return Err(Error {
+                    base: Report::msg(format!(
+                        "{} is not in the package index, so its override needs a version",
+                        name
+                    )),
+                });
+            }
+            None => {}
+        }
+    }
+
+    for (name, requirement) in requested.iter_mut() {
+        if let Some(version) = forced.get(split_features(name).0) {
+            *requirement = format!("={}", version);
+        }
+    }
+    Ok(index.with_forced_versions(&forced))
+}
+
+/// Everything the project manifest at `path` declares.
+fn manifest_packages(
+    project: Option<&Manifest>,
+    path: Option<&Path>,
+    no_dev: bool,
+) -> R<Vec<PackageSpec>, Error> {
+    let project = project.ok_or_else(|| Error {
+        base: Report::msg(format!(
+            "No packages given, and there is no manifest at {}",
+            path.unwrap_or(Path::new(manifest::MANIFEST_FILE)).display()
+        )),
+    })?;
+
+    Ok(manifest::requirements(project, !no_dev)
+        .into_iter()
+        .map(|(name, version)| PackageSpec {
+            name,
+            version: Some(version),
+        })
+        .collect())
+}
+
+/// The part of `selected` that the packages named in `roots` need on `target`, directly or
+/// through dependencies. A dependency on a package that is not selected is met by a selected
+/// package that provides or replaces it.
+pub fn needed_by(
+    roots: &[String],
+    selected: &[PackageVersion],
+    repo: &dyn Repository,
+    target: &Target,
+) -> Vec<PackageVersion> {
+    let release = |pv: &PackageVersion| {
+        repo.get_package(&pv.name)
+            .and_then(|package| find_release(package, &pv.version))
+    };
+    let stands_in = |pv: &PackageVersion, name: &str| {
+        release(pv).is_some_and(|r| {
+            r.provides.keys().any(|p| p.as_str() == name)
+                || r.replaces.keys().any(|p| p.as_str() == name)
+        })
+    };
+
+    let mut needed = BTreeSet::new();
+    let mut queue = roots
+        .iter()
+        .map(|root| split_features(root).0.to_string())
+        .collect::<VecDeque<_>>();
+    while let Some(name) = queue.pop_front() {
+        let mut meeting = selected
+            .iter()
+            .filter(|pv| pv.name == name)
+            .collect::<Vec<_>>();
+        if meeting.is_empty() {
+            meeting = selected.iter().filter(|pv| stands_in(pv, &name)).collect();
+        }
+        for package_version in meeting {
+            if needed.insert(package_version.name.clone()) {
+                if let Some(release) = release(package_version) {
+                    queue.extend(
+                        release_dependencies(release, &package_version.features, target)
+                            .into_keys(),
+                    );
+                }
+            }
+        }
+    }
+    selected
+        .iter()
+        .filter(|pv| needed.contains(&pv.name))
+        .cloned()
+        .collect()
+}
+
+/// Requirements for everything already installed explicitly, plus `packages`, which take
+/// precedence. Resolving these together keeps shared dependencies compatible with all of them.
+pub fn requested_packages(
+    installed: &InstalledPackages,
+    packages: &[PackageSpec],
+) -> BTreeMap<String, String> {
+    let mut requested = installed
+        .packages
+        .values()
+        .filter_map(|p| {
+            let name = match p.features.is_empty() {
+                true => p.name.clone(),
+                false => format!("{}[{}]", p.name, p.features.join(",")),
+            };
+            p.requirement.clone().map(|req| (name, req))
+        })
+        .collect::<BTreeMap<_, _>>();
+    for spec in packages.iter() {
+        let package = split_features(&spec.name).0;
+        requested.retain(|name, _| split_features(name).0 != package);
+        let version = spec.version.clone().unwrap_or("*".to_string());
+        requested.insert(spec.name.clone(), version);
+    }
+    requested
+}
+
+/// The features and version requirement `requested` asks of `package`, if it is requested.
+pub fn requested_for(
+    requested: &BTreeMap<String, String>,
+    package: &str,
+) -> Option<(Vec<String>, String)> {
+    requested.iter().find_map(|(name, version)| {
+        let (name, features) = split_features(name);
+        (name == package).then(|| {
+            let features = features.into_iter().map(str::to_string).collect();
+            (features, version.clone())
+        })
+    })
+}
+
+/// A strategy for `resolution` that stays close to what is installed, or failing that, locked.
+pub fn strategy(
+    resolution: Resolution,
+    installed: &InstalledPackages,
+    lock: Option<&Lockfile>,
+) -> Strategy {
+    let mut current = lock.map(|lock| lock.versions()).unwrap_or_default();
+    current.extend(
+        installed
+            .packages
+            .values()
+            .map(|p| (p.name.clone(), p.version.clone())),
+    );
+    Strategy::with_resolution(resolution).current(current)
+}
+
+/// Resolver limits from the `max_steps` and `timeout_seconds` parameters.
+pub fn limits(max_steps: Option<usize>, timeout_seconds: Option<u64>) -> ResolveLimits {
+    ResolveLimits {
+        max_steps,
+        timeout: timeout_seconds.map(std::time::Duration::from_secs),
+    }
+}
+
+/// The dependency index of `repo`. With `strict`, any invalid release is an error.
+pub fn index(repo: &dyn Repository, strict: bool) -> R<dependencies::Repository, Error> {
+    dependencies::Repository::new(repo, strict).map_err(|e| Error { base: e.into() })
+}
+
+/// Resolve `requested` (package name to version requirement) against `index` for `environment`,
+/// sorted by name.
+pub fn resolve(
+    requested: &BTreeMap<String, String>,
+    index: &dependencies::Repository,
+    strategy: &Strategy,
+    limits: ResolveLimits,
+    environment: &Environment,
+) -> R<Vec<PackageVersion>, Error> {
+    finish(resolver(requested, index, strategy, limits, environment)?)
+}
+
+/// Like [`resolve`], but taking the versions recorded in `lock` into account.
+pub fn resolve_locked(
+    requested: &BTreeMap<String, String>,
+    index: &dependencies::Repository,
+    strategy: &Strategy,
+    limits: ResolveLimits,
+    environment: &Environment,
+    lock: &Lockfile,
+    locking: Locking,
+) -> R<Vec<PackageVersion>, Error> {
+    finish(
+        resolver(requested, index, strategy, limits, environment)?.locked(lock.versions(), locking),
+    )
+}
+
+/// Resolve to exactly what `lock` records, or fail with what would have to change. Only the
+/// `lock_part` of a resolution is compared with `lock`.
+#[allow(clippy::too_many_arguments)]
+fn resolve_frozen(
+    requested: &BTreeMap<String, String>,
+    index: &dependencies::Repository,
+    strategy: &Strategy,
+    limits: ResolveLimits,
+    environment: &Environment,
+    lock: &Lockfile,
+    path: &Path,
+    lock_part: &dyn Fn(&[PackageVersion]) -> Vec<PackageVersion>,
+) -> R<Vec<PackageVersion>, Error> {
+    if let Ok(selected) = resolve_locked(
+        requested,
+        index,
+        strategy,
+        limits,
+        environment,
+        lock,
+        Locking::Require,
+    ) {
+        if lock.diff(&lock_part(&selected)).is_empty() {
+            return Ok(selected);
+        }
+    }
+
+    let selected = resolve_locked(
+        requested,
+        index,
+        strategy,
+        limits,
+        environment,
+        lock,
+        Locking::Prefer,
+    )?;
+    let changes = lock
+        .diff(&lock_part(&selected))
+        .iter()
+        .map(|change| format!("  {}", change))
+        .collect::<Vec<_>>();
+    Err(Error {
+        base: LockError::Stale {
+            path: path.to_path_buf(),
+            changes: changes.join("\n"),
+        }
+        .into(),
+    })
+}
+
+fn resolver(
+    requested: &BTreeMap<String, String>,
+    index: &dependencies::Repository,
+    strategy: &Strategy,
+    limits: ResolveLimits,
+    environment: &Environment,
+) -> R<PackageResolver, Error> {
+    let requirements = requested
+        .iter()
+        .map(|(name, version)| {
+            PackageRequirement::with_features(name.clone(), version.clone()).map_err(|e| Error {
+                base: Report::msg(format!(
+                    "Invalid requirement {} for {}: {}",
+                    version, name, e
+                )),
+            })
+        })
+        .collect::<R<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(
+        PackageResolver::new(requirements, index.clone(), strategy.clone())
+            .limits(limits)
+            .environment(environment),
+    )
+}
+
+fn finish(mut resolver: PackageResolver) -> R<Vec<PackageVersion>, Error> {
+    let mut selected = resolver
+        .resolve()
+        .map_err(|conflict| Error {
+            base: conflict.into(),
+        })?
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    selected.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(selected)
+}
+
+/// A lockfile recording `selected`, with the dependencies between them that apply on `target`.
+/// Checksums come from the installed packages, or from `previous` for anything that was not
+/// downloaded again.
+pub fn lock_selection(
+    selected: &[PackageVersion],
+    repo: &dyn Repository,
+    installed: &InstalledPackages,
+    previous: Option<&Lockfile>,
+    overrides: &BTreeMap<String, PackageOverride>,
+    target: &Target,
+) -> Lockfile {
+    let release = |pv: &PackageVersion| {
+        repo.get_package(&pv.name)
+            .and_then(|package| find_release(package, &pv.version))
+    };
+
+    let mut lock = Lockfile::default();
+    for package_version in selected {
+        let name = &package_version.name;
+        let same_version = |version: &semver::Version| version == &package_version.version;
+        let checksum = installed
+            .get(name)
+            .filter(|p| same_version(&p.version))
+            .and_then(|p| p.checksum.clone())
+            .or_else(|| {
+                previous
+                    .and_then(|lock| lock.get(name))
+                    .filter(|p| same_version(&p.version))
+                    .and_then(|p| p.checksum.clone())
+            });
+        let required_by = selected
+            .iter()
+            .filter(|other| {
+                release(other).is_some_and(|r| {
+                    release_dependencies(r, &other.features, target).contains_key(name)
+                })
+            })
+            .map(|other| other.name.clone())
+            .collect();
+
+        let overridden = overrides.get(name).cloned();
+        let url = match overridden.as_ref().filter(|o| o.has_source()) {
+            Some(_) => installed.get(name).map(|p| p.url.clone()),
+            None => release(package_version).map(|r| r.url.clone()),
+        };
+
+        lock.packages.insert(
+            name.clone(),
+            LockedPackage {
+                name: name.clone(),
+                version: package_version.version.clone(),
+                url: url.unwrap_or_default(),
+                source: repo.source_of(name).map(|s| s.to_string()),
+                checksum,
+                required_by,
+                features: package_version.features.clone(),
+                overridden,
+            },
+        );
+    }
+    lock
+}
+
+/// What `release` depends on when installed for `target` with `features` enabled, by package
+/// name and version requirement. Requirements on the same package, e.g. one asking for a
+/// feature, are combined.
+pub fn release_dependencies(
+    release: &Release,
+    features: &[String],
+    target: &Target,
+) -> BTreeMap<String, String> {
+    let optional = release
+        .features
+        .iter()
+        .filter(|(feature, _)| features.iter().any(|f| f == feature.as_str()))
+        .flat_map(|(_, dependencies)| dependencies.iter())
+        .map(|(name, dependency)| (name.as_str(), dependency));
+    let required = release
+        .dependencies
+        .iter()
+        .map(|(name, dependency)| (name.as_str(), dependency));
+
+    let mut dependencies = BTreeMap::<String, String>::new();
+    for (name, dependency) in required.chain(optional) {
+        if !dependency_condition(dependency).is_none_or(|condition| condition.matches(target)) {
+            continue;
+        }
+        let requirement = dependency.requirement();
+        dependencies
+            .entry(split_features(name).0.to_string())
+            .and_modify(|existing| {
+                if existing != requirement {
+                    *existing = format!("{}, {}", existing, requirement);
+                }
+            })
+            .or_insert_with(|| requirement.to_string());
+    }
+    dependencies
+}
+
+/// Download and unpack a single resolved package, and record it in `installed`. Fails if the
+/// download does not match `expected_checksum`. With an overridden `source`, the package is
+/// copied or cloned from there instead. Returns the previously installed entry, if any.
+#[allow(clippy::too_many_arguments)]
+pub async fn install_package(
+    package_version: &PackageVersion,
+    requirement: Option<(Vec<String>, String)>,
+    expected_checksum: Option<String>,
+    source: Option<&PackageOverride>,
+    target: &Target,
+    repo: &dyn Repository,
+    installed: &mut InstalledPackages,
+    repository_path: &Path,
+) -> R<Option<InstalledPackage>, Error> {
+    let name = package_version.name.clone();
+    let release = repo
+        .get_package(&name)
+        .and_then(|package| find_release(package, &package_version.version));
+    let destination = package_path(repository_path, &name).map_err(|e| Error { base: e.into() })?;
+
+    let (url, checksum) = match source {
+        Some(PackageOverride {
+            path: Some(path), ..
+        }) => {
+            download::copy_dir(Path::new(path), &destination)
+                .map_err(|e| Error { base: e.into() })?;
+            let path = std::path::absolute(path).unwrap_or_else(|_| path.into());
+            (format!("file://{}", path.display()), None)
+        }
+        Some(PackageOverride {
+            git: Some(git),
+            rev,
+            ..
+        }) => {
+            download::clone_git(git, rev.as_deref(), &destination)
+                .map_err(|e| Error { base: e.into() })?;
+            let url = match rev {
+                Some(rev) => format!("{}#{}", git, rev),
+                None => git.clone(),
+            };
+            (url, None)
+        }
+        _ => {
+            let release = release.ok_or_else(|| Error {
+                base: Report::msg(format!(
+                    "Release {} {} is missing from the repository",
+                    name, package_version.version
+                )),
+            })?;
+            let data = download::fetch(&release.url)
+                .await
+                .map_err(|e| Error { base: e.into() })?;
+            let checksum = lockfile::checksum(&data);
+            if let Some(expected) = expected_checksum.filter(|expected| expected != &checksum) {
+                return Err(Error {
+                    base: LockError::Checksum {
+                        name,
+                        expected,
+                        actual: checksum,
+                    }
+                    .into(),
+                });
+            }
+            download::unpack(&release.url, &data, &destination)
+                .map_err(|e| Error { base: e.into() })?;
+            (release.url.clone(), Some(checksum))
+        }
+    };
+
+    let required_by = package_version
+        .required_by
+        .iter()
+        .map(|req| split_features(&req.name).0.to_string())
+        .filter(|req_name| req_name != &name)
+        .collect();
+    let dependencies = release
+        .map(|release| release_dependencies(release, &package_version.features, target))
+        .unwrap_or_default();
+    let provides = release
+        .iter()
+        .flat_map(|release| {
+            let provides = release
+                .provides
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.clone()));
+            let replaces = release
+                .replaces
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.clone()));
+            provides.chain(replaces)
+        })
+        .collect();
+
+    let (features, requirement) = match requirement {
+        Some((features, version)) => (features, Some(version)),
+        None => (Vec::new(), None),
+    };
+    let previous = installed.packages.insert(
+        name.clone(),
+        InstalledPackage {
+            name,
+            version: package_version.version.clone(),
+            url,
+            requirement,
+            features,
+            required_by,
+            dependencies,
+            checksum,
+            provides,
+        },
+    );
+
+    // Record progress after every package, so a failed download leaves an accurate list.
+    installed
+        .save(repository_path)
+        .map_err(|e| Error { base: e.into() })?;
+
+    Ok(previous)
+}
+//////////////////////////////////////////////////////////////////////////////
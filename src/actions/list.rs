@@ -1,15 +1,43 @@
+use crate::core::installed::InstalledPackages;
+use crate::core::repository::{latest_version, Repository};
 use miette::Report;
 use miette::Result as R;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    #[default]
+    All,
+    Installed,
+    Available,
+    Upgradable,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Parameters {
     pub repository_path: String,
     pub repository_url: String,
+    #[serde(default)]
+    pub filter: Filter,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Result {}
+pub struct Row {
+    pub name: String,
+    pub latest_version: Option<String>,
+    pub installed_version: Option<String>,
+    pub upgradable: bool,
+    pub description: String,
+    pub license: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub packages: Vec<Row>,
+}
 
 pub struct Error {
     pub base: Report,
@@ -31,8 +59,54 @@ impl std::fmt::Display for Error {
 }
 
 //////////////////////////////////////////////////////////////////////////////
-pub fn run(_: &Parameters) -> R<Result, Error> {
-    // Implementation of the function
-    Ok(Result {})
+pub fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let installed = InstalledPackages::load(Path::new(&params.repository_path))
+        .map_err(|e| Error { base: e.into() })?;
+
+    let mut rows = BTreeMap::new();
+    for package in repo.get_packages() {
+        let latest = latest_version(package);
+        let installed_version = installed.get(&package.name).map(|p| &p.version);
+        let upgradable = match (&latest, installed_version) {
+            (Some(latest), Some(current)) => latest > current,
+            _ => false,
+        };
+
+        rows.insert(
+            package.name.clone(),
+            Row {
+                name: package.name.clone(),
+                latest_version: latest.map(|v| v.to_string()),
+                installed_version: installed_version.map(|v| v.to_string()),
+                upgradable,
+                description: package.description.clone(),
+                license: package.license.clone(),
+            },
+        );
+    }
+
+    // Installed packages that are no longer in the index are still worth showing.
+    for package in installed.packages.values() {
+        rows.entry(package.name.clone()).or_insert_with(|| Row {
+            name: package.name.clone(),
+            latest_version: None,
+            installed_version: Some(package.version.to_string()),
+            upgradable: false,
+            description: String::new(),
+            license: String::new(),
+        });
+    }
+
+    let packages = rows
+        .into_values()
+        .filter(|row| match params.filter {
+            Filter::All => true,
+            Filter::Installed => row.installed_version.is_some(),
+            Filter::Available => row.installed_version.is_none(),
+            Filter::Upgradable => row.upgradable,
+        })
+        .collect();
+
+    Ok(Result { packages })
 }
 //////////////////////////////////////////////////////////////////////////////
@@ -1 +1,9 @@
+pub mod config;
+pub mod info;
+pub mod install;
 pub mod list;
+pub mod outdated;
+pub mod repo;
+pub mod search;
+pub mod uninstall;
+pub mod update;
@@ -0,0 +1,124 @@
+use crate::core::dependencies::{self, split_features, PackageRequirement, Strategy};
+use crate::core::installed::InstalledPackages;
+use crate::core::repository::Repository;
+use miette::Report;
+use miette::Result as R;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_path: String,
+    pub repository_url: String,
+    /// Consider prerelease versions as candidates.
+    #[serde(default)]
+    pub include_prerelease: bool,
+    /// Fail on invalid releases in the package index instead of skipping them.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Row {
+    pub name: String,
+    pub installed: String,
+    /// The requirements currently placed on this package by the user and installed dependents.
+    pub requirements: Vec<String>,
+    /// Newest version that still satisfies every current requirement.
+    pub compatible: Option<String>,
+    /// Newest version overall, if it needs a requirement to change.
+    pub breaking: Option<String>,
+    /// Whether the installed version has been yanked, and why.
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub yanked_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub packages: Vec<Row>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OutdatedError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let installed = InstalledPackages::load(Path::new(&params.repository_path))
+        .map_err(|e| Error { base: e.into() })?;
+    let index =
+        dependencies::Repository::new(repo, params.strict).map_err(|e| Error { base: e.into() })?;
+    let strategy = Strategy::new().avoid_prerelease(!params.include_prerelease);
+
+    let mut packages = Vec::new();
+    for package in installed.packages.values() {
+        let mut requirements = package.requirement.iter().cloned().collect::<Vec<_>>();
+        // Requirements asking for features of the package constrain it just the same.
+        requirements.extend(
+            installed
+                .packages
+                .values()
+                .flat_map(|other| other.dependencies.iter())
+                .filter(|(name, _)| split_features(name).0 == package.name)
+                .map(|(_, requirement)| requirement.clone()),
+        );
+
+        let parsed = requirements
+            .iter()
+            .map(|req| PackageRequirement::new(package.name.clone(), req.clone()))
+            .collect::<R<Vec<_>, _>>()
+            .map_err(|e| Error {
+                base: Report::msg(format!("Invalid requirement on {}: {}", package.name, e)),
+            })?;
+
+        let newer = strategy
+            .filter_versions(index.get_versions(&package.name))
+            .into_iter()
+            .map(|pv| pv.version)
+            .filter(|v| v > &package.version && !index.is_yanked(&package.name, v))
+            .collect::<Vec<_>>();
+
+        let compatible = newer
+            .iter()
+            .filter(|v| parsed.iter().all(|req| req.matches(v)))
+            .max()
+            .cloned();
+        let latest = newer.iter().max().cloned();
+        let breaking = latest.filter(|latest| Some(latest) != compatible.as_ref());
+
+        let yanked = index.is_yanked(&package.name, &package.version);
+        if compatible.is_some() || breaking.is_some() || yanked {
+            packages.push(Row {
+                name: package.name.clone(),
+                installed: package.version.to_string(),
+                requirements,
+                compatible: compatible.map(|v| v.to_string()),
+                breaking: breaking.map(|v| v.to_string()),
+                yanked,
+                yanked_reason: index
+                    .yank_reason(&package.name, &package.version)
+                    .map(|r| r.to_string()),
+            });
+        }
+    }
+
+    Ok(Result { packages })
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,273 @@
+use crate::core::config::{self, Config, ConfigError};
+use crate::core::installed::check_directory_name;
+use crate::core::repository::RepositoryError;
+use crate::core::settings::{RepositoryAuth, RepositorySource};
+use miette::Report;
+use miette::Result as R;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Parameters {
+    Add {
+        name: String,
+        url: String,
+        #[serde(default)]
+        priority: i64,
+        #[serde(default)]
+        token: Option<String>,
+        #[serde(default)]
+        project: bool,
+    },
+    Remove {
+        name: String,
+        #[serde(default)]
+        project: bool,
+    },
+    Pin {
+        package: String,
+        repository: String,
+        #[serde(default)]
+        project: bool,
+    },
+    Unpin {
+        package: String,
+        #[serde(default)]
+        project: bool,
+    },
+    List,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Row {
+    pub name: String,
+    pub url: String,
+    pub priority: i64,
+    pub authenticated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    /// Effective repositories, highest priority first.
+    pub repositories: Vec<Row>,
+    pub pins: BTreeMap<String, String>,
+    /// The config file a change was written to.
+    pub written: Option<String>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RepoError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters, config: &Config) -> R<Result, Error> {
+    let written = match params {
+        Parameters::List => None,
+
+        Parameters::Add {
+            name,
+            url,
+            priority,
+            token,
+            project,
+        } => {
+            let path = config_path(config, *project)?;
+            check_directory_name(name).map_err(|reason| Error {
+                base: RepositoryError::InvalidName {
+                    name: name.clone(),
+                    reason,
+                }
+                .into(),
+            })?;
+            edit_repositories(config, path, |repositories| {
+                let source = RepositorySource {
+                    name: name.clone(),
+                    url: url.clone(),
+                    priority: *priority,
+                    auth: token.clone().map(|token| RepositoryAuth {
+                        token,
+                        header: None,
+                    }),
+                };
+                match repositories.iter_mut().find(|r| &r.name == name) {
+                    Some(existing) => *existing = source,
+                    None => repositories.push(source),
+                }
+                Ok(())
+            })?;
+            Some(path)
+        }
+
+        Parameters::Remove { name, project } => {
+            let path = config_path(config, *project)?;
+            edit_repositories(config, path, |repositories| {
+                let count = repositories.len();
+                repositories.retain(|r| &r.name != name);
+                if repositories.len() == count {
+                    return Err(Report::msg(format!(
+                        "No repository named {} in {}",
+                        name,
+                        path.display()
+                    )));
+                }
+                Ok(())
+            })?;
+            // Pins to the repository would otherwise hide their packages.
+            edit_pins(path, |pins| {
+                pins.retain(|_, repository| repository != name);
+            })?;
+            Some(path)
+        }
+
+        Parameters::Pin {
+            package,
+            repository,
+            project,
+        } => {
+            let path = config_path(config, *project)?;
+            let sources = config
+                .repository_sources()
+                .map_err(|e| Error { base: e.into() })?;
+            if !sources.iter().any(|source| &source.name == repository) {
+                return Err(Error {
+                    base: Report::msg(format!(
+                        "No repository named {} is configured (known: {})",
+                        repository,
+                        sources
+                            .iter()
+                            .map(|source| source.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                });
+            }
+            edit_pins(path, |pins| {
+                pins.insert(package.clone(), repository.clone());
+            })?;
+            Some(path)
+        }
+
+        Parameters::Unpin { package, project } => {
+            let path = config_path(config, *project)?;
+            edit_pins(path, |pins| {
+                pins.remove(package);
+            })?;
+            Some(path)
+        }
+    };
+
+    let config = config.reload().map_err(|e| Error { base: e.into() })?;
+    let mut sources = config
+        .repository_sources()
+        .map_err(|e| Error { base: e.into() })?;
+    sources.sort_by_key(|source| std::cmp::Reverse(source.priority));
+
+    Ok(Result {
+        repositories: sources
+            .into_iter()
+            .map(|source| Row {
+                name: source.name,
+                url: source.url,
+                priority: source.priority,
+                authenticated: source.auth.is_some(),
+            })
+            .collect(),
+        pins: config.pins().map_err(|e| Error { base: e.into() })?,
+        written: written.map(|path| path.display().to_string()),
+    })
+}
+
+fn config_path(config: &Config, project: bool) -> R<&Path, Error> {
+    if project {
+        config.project_path()
+    } else {
+        config.user_path()
+    }
+    .ok_or_else(|| Error {
+        base: Report::msg("Unable to determine the config file location"),
+    })
+}
+
+/// Change the repository list stored in one config file. If the file has no list yet, it
+/// starts from the default repository, so adding a repository does not drop it. Repositories
+/// from other files or the environment are left where they are.
+fn edit_repositories(
+    config: &Config,
+    path: &Path,
+    edit: impl FnOnce(&mut Vec<RepositorySource>) -> R<(), Report>,
+) -> R<(), Error> {
+    let repository_url = config
+        .get("repository_url")
+        .map_err(|e| Error { base: e.into() })?
+        .value;
+
+    config::edit_file(path, |values| {
+        // The file's own default, if it sets one.
+        let repository_url = match values.get("repository_url") {
+            Some(Value::String(url)) => url.clone(),
+            _ => repository_url,
+        };
+        let mut repositories = match values.get("repositories") {
+            Some(value) => {
+                serde_yaml::from_value(value.clone()).map_err(|e| ConfigError::InvalidValue {
+                    key: "repositories".to_string(),
+                    value: String::new(),
+                    reason: e.to_string(),
+                })?
+            }
+            None => vec![RepositorySource::default_for(&repository_url)],
+        };
+        edit(&mut repositories)?;
+        values.insert(
+            "repositories".to_string(),
+            serde_yaml::to_value(&repositories).unwrap_or(Value::Null),
+        );
+        Ok::<_, Report>(())
+    })
+    .map_err(|base| Error { base })
+}
+
+fn edit_pins(path: &Path, edit: impl FnOnce(&mut BTreeMap<String, String>)) -> R<(), Error> {
+    config::edit_file(path, |values| {
+        let mut pins: BTreeMap<String, String> = match values.get("pins") {
+            Some(value) => {
+                serde_yaml::from_value(value.clone()).map_err(|e| ConfigError::InvalidValue {
+                    key: "pins".to_string(),
+                    value: String::new(),
+                    reason: e.to_string(),
+                })?
+            }
+            None => BTreeMap::new(),
+        };
+        edit(&mut pins);
+        if pins.is_empty() {
+            values.remove("pins");
+        } else {
+            values.insert(
+                "pins".to_string(),
+                serde_yaml::to_value(&pins).unwrap_or(Value::Null),
+            );
+        }
+        Ok::<_, ConfigError>(())
+    })
+    .map_err(|e| Error { base: e.into() })
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,182 @@
+use crate::core::repository::{latest_version, Repository};
+use crate::specs::Package;
+use miette::Report;
+use miette::Result as R;
+use serde::{Deserialize, Serialize};
+
+/// Minimum similarity for a word to count as a misspelled match of a search term.
+const FUZZY_THRESHOLD: f64 = 0.75;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_url: String,
+    pub terms: Vec<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Name,
+    Description,
+    Authors,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Row {
+    pub name: String,
+    pub latest_version: Option<String>,
+    pub license: String,
+    pub description: String,
+    pub score: f64,
+    pub matched: Vec<Field>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub packages: Vec<Row>,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SearchError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let terms = params
+        .terms
+        .iter()
+        .flat_map(|t| t.split_whitespace())
+        .map(|t| t.to_lowercase())
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Err(Error {
+            base: Report::msg("No search terms given"),
+        });
+    }
+
+    let mut packages = repo
+        .get_packages()
+        .into_iter()
+        .filter_map(|package| {
+            let (score, matched) = score_package(package, &terms)?;
+            Some(Row {
+                name: package.name.clone(),
+                latest_version: latest_version(package).map(|v| v.to_string()),
+                license: package.license.clone(),
+                description: package.description.clone(),
+                score,
+                matched,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    packages.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.name.cmp(&b.name)));
+    if let Some(limit) = params.limit {
+        packages.truncate(limit);
+    }
+
+    Ok(Result { packages })
+}
+
+/// Score a package against every term. Each term has to match somewhere, or the package
+/// is not a result at all.
+fn score_package(package: &Package, terms: &[String]) -> Option<(f64, Vec<Field>)> {
+    let name = package.name.to_lowercase();
+    let description = package.description.to_lowercase();
+    let authors = package.authors.join(" ").to_lowercase();
+
+    let mut total = 0.0;
+    let mut matched = Vec::new();
+    for term in terms {
+        let candidates = [
+            (Field::Name, score_name(&name, term)),
+            (Field::Description, score_text(&description, term) * 0.3),
+            (Field::Authors, score_text(&authors, term) * 0.2),
+        ];
+        let mut term_score = 0.0;
+        for (field, score) in candidates {
+            if score > 0.0 {
+                term_score += score;
+                if !matched.contains(&field) {
+                    matched.push(field);
+                }
+            }
+        }
+        if term_score == 0.0 {
+            return None;
+        }
+        total += term_score;
+    }
+
+    Some((total, matched))
+}
+
+fn score_name(name: &str, term: &str) -> f64 {
+    if name == term {
+        100.0
+    } else if name.starts_with(term) {
+        80.0
+    } else if name.contains(term) {
+        60.0
+    } else {
+        // Names are often compound (e.g. `wslib`, `miSCellaneous_lib`), so compare against
+        // the parts as well as the whole.
+        let best = std::iter::once(name)
+            .chain(words(name))
+            .map(|part| similarity(part, term))
+            .fold(0.0, f64::max);
+        if best >= FUZZY_THRESHOLD {
+            50.0 * best
+        } else {
+            0.0
+        }
+    }
+}
+
+fn score_text(text: &str, term: &str) -> f64 {
+    if words(text).any(|word| word == term) {
+        100.0
+    } else if text.contains(term) {
+        70.0
+    } else {
+        let best = words(text)
+            .map(|word| similarity(word, term))
+            .fold(0.0, f64::max);
+        if best >= FUZZY_THRESHOLD {
+            50.0 * best
+        } else {
+            0.0
+        }
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+fn similarity(word: &str, term: &str) -> f64 {
+    // Very short terms match too much when fuzzy.
+    if term.chars().count() < 4 {
+        return 0.0;
+    }
+    strsim::normalized_damerau_levenshtein(word, term)
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,210 @@
+use crate::core::installed::{package_path, InstalledPackages};
+use miette::Result as R;
+use miette::{Diagnostic, Report};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::path::Path;
+use thiserror::Error as ThisError;
+
+fn default_remove_orphans() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_path: String,
+    pub repository_url: String,
+    pub packages: Vec<String>,
+    /// Remove packages even if other installed packages depend on them.
+    #[serde(default)]
+    pub force: bool,
+    /// Also remove dependencies that are no longer needed by anything else.
+    #[serde(default = "default_remove_orphans")]
+    pub remove_orphans: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemovedRow {
+    pub name: String,
+    pub version: String,
+    /// True if this package was removed because nothing needs it anymore.
+    pub orphan: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub removed: Vec<RemovedRow>,
+}
+
+#[derive(Debug, ThisError, Diagnostic)]
+pub enum UninstallError {
+    #[error("{name} is not installed")]
+    NotInstalled { name: String },
+
+    #[error("{name} is still required by {}", dependents.join(", "))]
+    #[diagnostic(help(
+        "Uninstall the dependent packages first, or pass --force to remove {name} anyway."
+    ))]
+    StillRequired {
+        name: String,
+        dependents: Vec<String>,
+    },
+
+    #[error("Failed to remove {name}: {source}")]
+    IO {
+        name: String,
+        source: std::io::Error,
+    },
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UninstallError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub fn run(params: &Parameters) -> R<Result, Error> {
+    let repository_path = Path::new(&params.repository_path);
+    let mut installed =
+        InstalledPackages::load(repository_path).map_err(|e| Error { base: e.into() })?;
+
+    let targets = params.packages.iter().cloned().collect::<BTreeSet<_>>();
+    for name in targets.iter() {
+        let Some(package) = installed.get(name) else {
+            return Err(Error {
+                base: UninstallError::NotInstalled { name: name.clone() }.into(),
+            });
+        };
+
+        // Dependencies on what the package provides or replaces count too, unless a package
+        // that stays installed meets them.
+        let unmet = std::iter::once(name)
+            .chain(package.provides.keys())
+            .filter(|met| {
+                !installed
+                    .providers(met)
+                    .any(|provider| !targets.contains(&provider.name))
+            })
+            .collect::<Vec<_>>();
+        let dependents = installed
+            .packages
+            .values()
+            .filter(|p| {
+                !targets.contains(&p.name)
+                    && unmet.iter().any(|met| p.dependencies.contains_key(*met))
+            })
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>();
+        if !dependents.is_empty() && !params.force {
+            return Err(Error {
+                base: UninstallError::StillRequired {
+                    name: name.clone(),
+                    dependents,
+                }
+                .into(),
+            });
+        }
+    }
+
+    let mut removals = targets
+        .iter()
+        .map(|name| (name.clone(), false))
+        .collect::<Vec<_>>();
+
+    if params.remove_orphans {
+        // Dependencies pulled in for the removed packages, either directly or via their chains.
+        let mut candidates = BTreeSet::new();
+        let mut queue = targets.iter().cloned().collect::<VecDeque<_>>();
+        while let Some(name) = queue.pop_front() {
+            if let Some(package) = installed.get(&name) {
+                for dep in package.dependencies.keys() {
+                    for provider in installed.providers(dep) {
+                        if candidates.insert(provider.name.clone()) {
+                            queue.push_back(provider.name.clone());
+                        }
+                    }
+                }
+            }
+        }
+        for package in installed.packages.values() {
+            if package.required_by.iter().any(|r| targets.contains(r)) {
+                candidates.insert(package.name.clone());
+            }
+        }
+
+        // Anything still reachable from an explicitly installed package has to stay.
+        let mut needed = BTreeSet::new();
+        let mut queue = installed
+            .packages
+            .values()
+            .filter(|p| p.requirement.is_some() && !targets.contains(&p.name))
+            .map(|p| p.name.clone())
+            .collect::<VecDeque<_>>();
+        while let Some(name) = queue.pop_front() {
+            if targets.contains(&name) || !needed.insert(name.clone()) {
+                continue;
+            }
+            if let Some(package) = installed.get(&name) {
+                for dep in package.dependencies.keys() {
+                    queue.extend(installed.providers(dep).map(|p| p.name.clone()));
+                }
+            }
+        }
+
+        for name in candidates {
+            let orphaned = !targets.contains(&name)
+                && !needed.contains(&name)
+                && installed
+                    .get(&name)
+                    .is_some_and(|p| p.requirement.is_none());
+            if orphaned {
+                removals.push((name, true));
+            }
+        }
+    }
+
+    let mut result = Result {
+        removed: Vec::new(),
+    };
+    for (name, orphan) in removals {
+        let path = package_path(repository_path, &name).map_err(|e| Error { base: e.into() })?;
+        if path.exists() {
+            std::fs::remove_dir_all(&path).map_err(|source| Error {
+                base: UninstallError::IO {
+                    name: name.clone(),
+                    source,
+                }
+                .into(),
+            })?;
+        }
+
+        if let Some(package) = installed.packages.remove(&name) {
+            result.removed.push(RemovedRow {
+                name,
+                version: package.version.to_string(),
+                orphan,
+            });
+        }
+    }
+
+    installed
+        .save(repository_path)
+        .map_err(|e| Error { base: e.into() })?;
+
+    Ok(result)
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,261 @@
+use crate::actions::install;
+use crate::core::dependencies::{Environment, PackageVersion, Resolution, Strategy};
+use crate::core::installed::{package_path, InstalledPackages};
+use crate::core::lockfile::Lockfile;
+use crate::core::manifest;
+use crate::core::repository::Repository;
+use crate::core::settings::PackageOverride;
+use crate::core::target::Target;
+use miette::Report;
+use miette::Result as R;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Parameters {
+    pub repository_path: String,
+    pub repository_url: String,
+    /// Only work out the plan, without downloading or removing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Lockfile to record the new versions in. Its current versions are not preferred.
+    #[serde(default)]
+    pub lockfile: Option<String>,
+    /// Project manifest whose repositories are searched and whose dependencies the lockfile
+    /// records. Without one, the lockfile records everything installed.
+    #[serde(default)]
+    pub manifest: Option<String>,
+    /// Which compatible versions to pick.
+    #[serde(default)]
+    pub resolution: Resolution,
+    /// Give up resolving after this many backtracking steps.
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+    /// Give up resolving after this many seconds.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Fail on invalid releases in the package index instead of skipping them.
+    #[serde(default)]
+    pub strict: bool,
+    /// Platform to pick platform-specific dependencies for, instead of the current one.
+    #[serde(default)]
+    pub target: Option<Target>,
+    /// SuperCollider version releases must work with. Not checked if unset.
+    #[serde(default)]
+    pub supercollider: Option<Version>,
+    /// Packages to take from a local directory, a git repository or a forced version instead
+    /// of the index, by package name.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, PackageOverride>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Change {
+    Install {
+        name: String,
+        version: String,
+    },
+    Upgrade {
+        name: String,
+        from: String,
+        to: String,
+    },
+    Downgrade {
+        name: String,
+        from: String,
+        to: String,
+    },
+    Remove {
+        name: String,
+        version: String,
+    },
+}
+
+impl Change {
+    pub fn name(&self) -> &str {
+        match self {
+            Change::Install { name, .. }
+            | Change::Upgrade { name, .. }
+            | Change::Downgrade { name, .. }
+            | Change::Remove { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Result {
+    pub plan: Plan,
+    /// False for a dry run, or if there was nothing to do.
+    pub applied: bool,
+}
+
+pub struct Error {
+    pub base: Report,
+}
+trait HasReport {
+    fn base(&self) -> &Report;
+}
+
+impl HasReport for Error {
+    fn base(&self) -> &Report {
+        &self.base
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UpdateError: {}", self.base)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////
+pub async fn run(params: &Parameters, repo: &dyn Repository) -> R<Result, Error> {
+    let repository_path = Path::new(&params.repository_path);
+    let mut installed =
+        InstalledPackages::load(repository_path).map_err(|e| Error { base: e.into() })?;
+
+    let mut requested = install::requested_packages(&installed, &[]);
+    let limits = install::limits(params.max_steps, params.timeout_seconds);
+    let index = install::index(repo, params.strict)
+        .and_then(|index| install::apply_overrides(index, &mut requested, &params.overrides))
+        .map_err(|e| Error { base: e.base })?;
+    let environment = Environment {
+        target: params.target.clone().unwrap_or_default(),
+        supercollider: params.supercollider.clone(),
+    };
+    let selected = install::resolve(
+        &requested,
+        &index,
+        &Strategy::with_resolution(params.resolution),
+        limits,
+        &environment,
+    )
+    .map_err(|e| Error { base: e.base })?;
+    let plan = make_plan(&installed, &selected);
+
+    if params.dry_run {
+        return Ok(Result {
+            plan,
+            applied: false,
+        });
+    }
+
+    for change in plan.changes.iter() {
+        if let Change::Remove { name, .. } = change {
+            let path = package_path(repository_path, name).map_err(|e| Error { base: e.into() })?;
+            if path.exists() {
+                std::fs::remove_dir_all(&path).map_err(|e| Error {
+                    base: Report::msg(format!("Failed to remove {}: {}", name, e)),
+                })?;
+            }
+            installed.packages.remove(name);
+            continue;
+        }
+
+        let package_version = selected
+            .iter()
+            .find(|pv| pv.name == change.name())
+            .expect("planned packages come from the resolution");
+        let requirement = install::requested_for(&requested, change.name());
+        install::install_package(
+            package_version,
+            requirement,
+            None,
+            params
+                .overrides
+                .get(change.name())
+                .filter(|o| o.has_source()),
+            &environment.target,
+            repo,
+            &mut installed,
+            repository_path,
+        )
+        .await
+        .map_err(|e| Error { base: e.base })?;
+    }
+
+    installed
+        .save(repository_path)
+        .map_err(|e| Error { base: e.into() })?;
+    if let Some(path) = params.lockfile.as_deref().map(Path::new) {
+        let previous = Lockfile::load(path).map_err(|e| Error { base: e.into() })?;
+        let project = params
+            .manifest
+            .as_deref()
+            .map(|manifest| manifest::load(Path::new(manifest)))
+            .transpose()
+            .map_err(|e| Error { base: e.into() })?
+            .flatten();
+        let roots = match &project {
+            Some(project) => manifest::requirements(project, true)
+                .into_keys()
+                .collect::<Vec<_>>(),
+            None => requested.keys().cloned().collect(),
+        };
+        install::lock_selection(
+            &install::needed_by(&roots, &selected, repo, &environment.target),
+            repo,
+            &installed,
+            previous.as_ref(),
+            &params.overrides,
+            &environment.target,
+        )
+        .save(path)
+        .map_err(|e| Error { base: e.into() })?;
+    }
+
+    Ok(Result {
+        applied: !plan.changes.is_empty(),
+        plan,
+    })
+}
+
+/// Diff a fresh resolution against what is currently installed.
+pub fn make_plan(installed: &InstalledPackages, selected: &[PackageVersion]) -> Plan {
+    let mut changes = Vec::new();
+    for package_version in selected {
+        let name = package_version.name.clone();
+        let to = package_version.version.to_string();
+        match installed.get(&name) {
+            None => changes.push(Change::Install { name, version: to }),
+            Some(existing) if existing.version < package_version.version => {
+                changes.push(Change::Upgrade {
+                    name,
+                    from: existing.version.to_string(),
+                    to,
+                })
+            }
+            Some(existing) if existing.version > package_version.version => {
+                changes.push(Change::Downgrade {
+                    name,
+                    from: existing.version.to_string(),
+                    to,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Dependencies that nothing needs anymore. Explicitly installed packages are always
+    // part of the resolution, so they never end up here.
+    for package in installed.packages.values() {
+        if !selected.iter().any(|pv| pv.name == package.name) {
+            changes.push(Change::Remove {
+                name: package.name.clone(),
+                version: package.version.to_string(),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.name().cmp(b.name()));
+    Plan { changes }
+}
+//////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,78 @@
+use super::table;
+use crate::actions::config;
+use crate::{core::config::Config, Result};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ConfigArgs {
+    /// Show the effective value of a key and where it came from
+    Get { key: String },
+    /// Write a value to the user config (or the project config with --project)
+    Set {
+        key: String,
+        value: String,
+        #[arg(long)]
+        project: bool,
+    },
+    /// Show every effective value and where it came from
+    List,
+}
+
+pub(crate) async fn do_raw(
+    params: &config::Parameters,
+    config: &Config,
+) -> Result<config::Result, config::Error> {
+    config::run(params, config)
+}
+
+pub(crate) async fn do_cli(
+    args: ConfigArgs,
+    config: &Config,
+) -> Result<config::Result, config::Error> {
+    let parameters = make_parameters(args).await?;
+    do_raw(&parameters, config).await
+}
+
+pub(crate) async fn make_parameters(args: ConfigArgs) -> Result<config::Parameters, config::Error> {
+    let result = match args {
+        ConfigArgs::Get { key } => config::Parameters::Get { key },
+        ConfigArgs::Set {
+            key,
+            value,
+            project,
+        } => config::Parameters::Set {
+            key,
+            value,
+            project,
+        },
+        ConfigArgs::List => config::Parameters::List,
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<config::Parameters> {
+    let result = serde_json::from_str::<config::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &config::Result) -> String {
+    let rows = result
+        .entries
+        .iter()
+        .map(|entry| {
+            let source = if entry.origin.is_empty() {
+                entry.source.to_string()
+            } else {
+                format!("{} ({})", entry.source, entry.origin)
+            };
+            vec![entry.key.clone(), entry.value.clone(), source]
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = table(&["Key", "Value", "Source"], &rows);
+    if let Some(path) = &result.written {
+        output = format!("Wrote {}\n{}", path, output);
+    }
+    output
+}
@@ -0,0 +1,111 @@
+use crate::actions::info;
+use crate::core::supercollider;
+use crate::core::target::Target;
+use crate::{core::repository::Repository, core::settings::Settings, Result};
+
+#[derive(Debug, clap::Args)]
+pub struct InfoArgs {
+    /// Package to describe
+    package: String,
+
+    #[arg(long)]
+    repository_path: Option<String>,
+
+    /// Platform to check platform-specific dependencies for, as <os>-<arch>, e.g.
+    /// macos-aarch64. Defaults to the current platform.
+    #[arg(long)]
+    target: Option<Target>,
+}
+
+pub(crate) async fn do_raw(
+    params: &info::Parameters,
+    repo: &dyn Repository,
+) -> Result<info::Result, info::Error> {
+    info::run(params, repo)
+}
+
+pub(crate) async fn do_cli(
+    args: InfoArgs,
+    settings: &Settings,
+    repo: &dyn Repository,
+) -> Result<info::Result, info::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters, repo).await
+}
+
+pub(crate) async fn make_parameters(
+    args: InfoArgs,
+    settings: &Settings,
+) -> Result<info::Parameters, info::Error> {
+    let result = info::Parameters {
+        repository_path: args
+            .repository_path
+            .unwrap_or(settings.global_repository_path.clone()),
+        repository_url: settings.repository_url.clone(),
+        package: args.package,
+        strict: settings.strict_repository,
+        max_steps: settings.resolve_limits.max_steps,
+        timeout_seconds: settings.resolve_limits.timeout.map(|t| t.as_secs()),
+        target: args.target,
+        supercollider: settings
+            .supercollider_version
+            .clone()
+            .or_else(supercollider::detect),
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<info::Parameters> {
+    let result = serde_json::from_str::<info::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &info::Result) -> String {
+    let mut lines = vec![
+        result.name.clone(),
+        format!("  {}", result.description),
+        String::new(),
+        format!("  Authors:   {}", result.authors.join(", ")),
+        format!("  License:   {}", result.license),
+        format!("  Homepage:  {}", result.url),
+        format!("  Source:    {}", result.repo),
+        format!(
+            "  Installed: {}",
+            result.installed_version.as_deref().unwrap_or("-")
+        ),
+        String::new(),
+        "Releases:".to_string(),
+    ];
+
+    for release in result.releases.iter() {
+        let mut notes = Vec::new();
+        if release.installed {
+            notes.push("installed");
+        }
+        let yanked = match &release.yanked_reason {
+            Some(reason) => format!("yanked: {}", reason),
+            None => "yanked".to_string(),
+        };
+        if release.yanked {
+            notes.push(&yanked);
+        }
+        if !release.installable {
+            notes.push("not installable: dependencies cannot be satisfied");
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        };
+        let marker = if release.installed { "*" } else { " " };
+        lines.push(format!("{} {}{}", marker, release.version, notes));
+
+        for (name, requirement) in release.dependencies.iter() {
+            lines.push(format!("      {} {}", name, requirement));
+        }
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,139 @@
+use crate::actions::install::{self, PackageSpec};
+use crate::core::dependencies::Resolution;
+use crate::core::lockfile::project_lockfile;
+use crate::core::manifest::MANIFEST_FILE;
+use crate::core::supercollider;
+use crate::core::target::Target;
+use crate::{core::repository::Repository, core::settings::Settings, Result};
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct InstallArgs {
+    /// Packages to install, as `name` or `name@requirement`, with features to enable in
+    /// brackets, e.g. `package1[gui]@^1.2`. Without any, installs what the project manifest
+    /// declares.
+    packages: Vec<String>,
+
+    #[arg(long)]
+    repository_path: Option<String>,
+
+    /// Lockfile to prefer versions from and record the result in. Defaults to baryon.lock next
+    /// to the project manifest, if there is one.
+    #[arg(long)]
+    lockfile: Option<PathBuf>,
+
+    /// Fail if the lockfile would change
+    #[arg(long)]
+    locked: bool,
+
+    /// Project manifest to install from when no packages are given, and to take repositories
+    /// and overrides from. Defaults to baryon.yaml in the current directory.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Leave out the manifest's dev-dependencies
+    #[arg(long)]
+    no_dev: bool,
+
+    /// Which compatible versions to pick: highest, lowest, lowest-direct or minimal-change
+    #[arg(long, default_value_t = Resolution::Highest)]
+    resolution: Resolution,
+
+    /// Platform to resolve platform-specific dependencies for, as <os>-<arch>, e.g.
+    /// macos-aarch64. Defaults to the current platform.
+    #[arg(long)]
+    target: Option<Target>,
+}
+
+impl InstallArgs {
+    /// The project manifest to use, whether or not it exists.
+    pub(crate) fn manifest_path(&self) -> PathBuf {
+        self.manifest
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(MANIFEST_FILE))
+    }
+}
+
+pub(crate) async fn do_raw(
+    params: &install::Parameters,
+    repo: &dyn Repository,
+) -> Result<install::Result, install::Error> {
+    install::run(params, repo).await
+}
+
+pub(crate) async fn do_cli(
+    args: InstallArgs,
+    settings: &Settings,
+    repo: &dyn Repository,
+) -> Result<install::Result, install::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters, repo).await
+}
+
+pub(crate) async fn make_parameters(
+    args: InstallArgs,
+    settings: &Settings,
+) -> Result<install::Parameters, install::Error> {
+    let manifest = args.manifest_path();
+    let result = install::Parameters {
+        repository_path: args
+            .repository_path
+            .unwrap_or(settings.global_repository_path.clone()),
+        repository_url: settings.repository_url.clone(),
+        packages: args
+            .packages
+            .iter()
+            .map(|spec| PackageSpec::parse(spec))
+            .collect(),
+        lockfile: args
+            .lockfile
+            .or_else(|| project_lockfile(&manifest))
+            .map(|path| path.to_string_lossy().to_string()),
+        locked: args.locked,
+        manifest: Some(manifest.to_string_lossy().to_string()),
+        no_dev: args.no_dev,
+        resolution: args.resolution,
+        max_steps: settings.resolve_limits.max_steps,
+        timeout_seconds: settings.resolve_limits.timeout.map(|t| t.as_secs()),
+        strict: settings.strict_repository,
+        target: args.target,
+        supercollider: settings
+            .supercollider_version
+            .clone()
+            .or_else(supercollider::detect),
+        overrides: settings.overrides.clone(),
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<install::Parameters> {
+    let result = serde_json::from_str::<install::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &install::Result) -> String {
+    let mut lines = result
+        .installed
+        .iter()
+        .map(|row| match &row.previous_version {
+            Some(previous) => format!("Installed {} {} (was {})", row.name, row.version, previous),
+            None => format!("Installed {} {}", row.name, row.version),
+        })
+        .collect::<Vec<_>>();
+
+    if !result.unchanged.is_empty() {
+        lines.push(format!(
+            "Already up to date: {}",
+            result.unchanged.join(", ")
+        ));
+    }
+    if lines.is_empty() {
+        lines.push("Nothing to install".to_string());
+    }
+    for warning in result.warnings.iter() {
+        lines.push(format!("Warning: {}", warning));
+    }
+    lines.join("\n")
+}
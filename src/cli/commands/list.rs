@@ -1,17 +1,30 @@
-use crate::actions::list;
+use super::table;
+use crate::actions::list::{self, Filter};
 use crate::{core::repository::Repository, core::settings::Settings, Result};
 
 #[derive(Debug, clap::Args)]
 pub struct ListArgs {
     repository_path: Option<String>,
     repository_url: Option<String>,
+
+    /// Only show installed packages
+    #[arg(long, group = "filter")]
+    installed: bool,
+
+    /// Only show packages that are not installed
+    #[arg(long, group = "filter")]
+    available: bool,
+
+    /// Only show installed packages with a newer release
+    #[arg(long, group = "filter")]
+    upgradable: bool,
 }
 
 pub(crate) async fn do_raw(
     params: &list::Parameters,
-    _repo: &dyn Repository,
+    repo: &dyn Repository,
 ) -> Result<list::Result, list::Error> {
-    list::run(params)
+    list::run(params, repo)
 }
 
 pub(crate) async fn do_cli(
@@ -27,6 +40,16 @@ pub(crate) async fn make_parameters(
     args: ListArgs,
     settings: &Settings,
 ) -> Result<list::Parameters, list::Error> {
+    let filter = if args.installed {
+        Filter::Installed
+    } else if args.available {
+        Filter::Available
+    } else if args.upgradable {
+        Filter::Upgradable
+    } else {
+        Filter::All
+    };
+
     let result = list::Parameters {
         repository_path: args
             .repository_path
@@ -34,6 +57,7 @@ pub(crate) async fn make_parameters(
         repository_url: args
             .repository_url
             .unwrap_or(settings.repository_url.clone()),
+        filter,
     };
     Ok(result)
 }
@@ -44,3 +68,29 @@ pub(crate) fn from_json(json: &str) -> Result<list::Parameters> {
 
     Ok(result)
 }
+
+pub(crate) fn format(result: &list::Result) -> String {
+    let rows = result
+        .packages
+        .iter()
+        .map(|row| {
+            let installed = match (&row.installed_version, row.upgradable) {
+                (Some(version), true) => format!("{} (upgradable)", version),
+                (Some(version), false) => version.clone(),
+                (None, _) => "-".to_string(),
+            };
+            vec![
+                row.name.clone(),
+                row.latest_version.clone().unwrap_or("-".to_string()),
+                installed,
+                row.license.clone(),
+                row.description.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    table(
+        &["Name", "Latest", "Installed", "License", "Description"],
+        &rows,
+    )
+}
@@ -1 +1,34 @@
+pub mod config;
+pub mod info;
+pub mod install;
 pub mod list;
+pub mod outdated;
+pub mod repo;
+pub mod search;
+pub mod uninstall;
+pub mod update;
+
+/// Render rows as a plain left-aligned text table with a header line.
+pub(crate) fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths = headers.iter().map(|h| h.len()).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![format_row(headers.iter().map(|h| h.to_string()).collect())];
+    lines.extend(rows.iter().map(|row| format_row(row.clone())));
+    lines.join("\n")
+}
@@ -0,0 +1,91 @@
+use super::table;
+use crate::actions::outdated;
+use crate::{core::repository::Repository, core::settings::Settings, Result};
+
+#[derive(Debug, clap::Args)]
+pub struct OutdatedArgs {
+    /// Consider prerelease versions
+    #[arg(long)]
+    prerelease: bool,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub(crate) json: bool,
+
+    #[arg(long)]
+    repository_path: Option<String>,
+}
+
+pub(crate) async fn do_raw(
+    params: &outdated::Parameters,
+    repo: &dyn Repository,
+) -> Result<outdated::Result, outdated::Error> {
+    outdated::run(params, repo)
+}
+
+pub(crate) async fn do_cli(
+    args: OutdatedArgs,
+    settings: &Settings,
+    repo: &dyn Repository,
+) -> Result<outdated::Result, outdated::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters, repo).await
+}
+
+pub(crate) async fn make_parameters(
+    args: OutdatedArgs,
+    settings: &Settings,
+) -> Result<outdated::Parameters, outdated::Error> {
+    let result = outdated::Parameters {
+        repository_path: args
+            .repository_path
+            .unwrap_or(settings.global_repository_path.clone()),
+        repository_url: settings.repository_url.clone(),
+        include_prerelease: args.prerelease,
+        strict: settings.strict_repository,
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<outdated::Parameters> {
+    let result = serde_json::from_str::<outdated::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &outdated::Result) -> String {
+    if result.packages.is_empty() {
+        return "All installed packages are up to date".to_string();
+    }
+
+    let rows = result
+        .packages
+        .iter()
+        .map(|row| {
+            let installed = match (row.yanked, &row.yanked_reason) {
+                (true, Some(reason)) => format!("{} (yanked: {})", row.installed, reason),
+                (true, None) => format!("{} (yanked)", row.installed),
+                (false, _) => row.installed.clone(),
+            };
+            vec![
+                row.name.clone(),
+                installed,
+                row.compatible.clone().unwrap_or("-".to_string()),
+                row.breaking.clone().unwrap_or("-".to_string()),
+                row.requirements.join(", "),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    table(
+        &[
+            "Name",
+            "Installed",
+            "Compatible",
+            "Needs requirement change",
+            "Requirements",
+        ],
+        &rows,
+    )
+}
@@ -0,0 +1,120 @@
+use super::table;
+use crate::actions::repo;
+use crate::{core::config::Config, Result};
+
+#[derive(Debug, clap::Subcommand)]
+pub enum RepoArgs {
+    /// Add a repository, or replace one with the same name
+    Add {
+        name: String,
+        url: String,
+        /// Repositories with a higher priority win when several have the same package
+        #[arg(long, default_value_t = 0)]
+        priority: i64,
+        /// Bearer token, e.g. `'${PRIVATE_INDEX_TOKEN}'` to read it from the environment
+        #[arg(long)]
+        token: Option<String>,
+        #[arg(long)]
+        project: bool,
+    },
+    /// Remove a repository
+    Remove {
+        name: String,
+        #[arg(long)]
+        project: bool,
+    },
+    /// Always take a package from the given repository
+    Pin {
+        package: String,
+        repository: String,
+        #[arg(long)]
+        project: bool,
+    },
+    /// Remove a package pin
+    Unpin {
+        package: String,
+        #[arg(long)]
+        project: bool,
+    },
+    /// Show the configured repositories and pins
+    List,
+}
+
+pub(crate) async fn do_raw(
+    params: &repo::Parameters,
+    config: &Config,
+) -> Result<repo::Result, repo::Error> {
+    repo::run(params, config)
+}
+
+pub(crate) async fn do_cli(args: RepoArgs, config: &Config) -> Result<repo::Result, repo::Error> {
+    let parameters = make_parameters(args).await?;
+    do_raw(&parameters, config).await
+}
+
+pub(crate) async fn make_parameters(args: RepoArgs) -> Result<repo::Parameters, repo::Error> {
+    let result = match args {
+        RepoArgs::Add {
+            name,
+            url,
+            priority,
+            token,
+            project,
+        } => repo::Parameters::Add {
+            name,
+            url,
+            priority,
+            token,
+            project,
+        },
+        RepoArgs::Remove { name, project } => repo::Parameters::Remove { name, project },
+        RepoArgs::Pin {
+            package,
+            repository,
+            project,
+        } => repo::Parameters::Pin {
+            package,
+            repository,
+            project,
+        },
+        RepoArgs::Unpin { package, project } => repo::Parameters::Unpin { package, project },
+        RepoArgs::List => repo::Parameters::List,
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<repo::Parameters> {
+    let result = serde_json::from_str::<repo::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &repo::Result) -> String {
+    let rows = result
+        .repositories
+        .iter()
+        .map(|row| {
+            vec![
+                row.name.clone(),
+                row.priority.to_string(),
+                row.url.clone(),
+                if row.authenticated { "yes" } else { "no" }.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = table(&["Name", "Priority", "URL", "Auth"], &rows);
+    if !result.pins.is_empty() {
+        let pins = result
+            .pins
+            .iter()
+            .map(|(package, repository)| vec![package.clone(), repository.clone()])
+            .collect::<Vec<_>>();
+        output = format!("{}\n\n{}", output, table(&["Package", "Pinned to"], &pins));
+    }
+    if let Some(path) = &result.written {
+        output = format!("Wrote {}\n{}", path, output);
+    }
+    output
+}
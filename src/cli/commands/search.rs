@@ -0,0 +1,70 @@
+use super::table;
+use crate::actions::search;
+use crate::{core::repository::Repository, core::settings::Settings, Result};
+
+#[derive(Debug, clap::Args)]
+pub struct SearchArgs {
+    /// Words to look for in package names, descriptions and authors
+    #[arg(required = true)]
+    terms: Vec<String>,
+
+    /// Maximum number of results
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+pub(crate) async fn do_raw(
+    params: &search::Parameters,
+    repo: &dyn Repository,
+) -> Result<search::Result, search::Error> {
+    search::run(params, repo)
+}
+
+pub(crate) async fn do_cli(
+    args: SearchArgs,
+    settings: &Settings,
+    repo: &dyn Repository,
+) -> Result<search::Result, search::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters, repo).await
+}
+
+pub(crate) async fn make_parameters(
+    args: SearchArgs,
+    settings: &Settings,
+) -> Result<search::Parameters, search::Error> {
+    let result = search::Parameters {
+        repository_url: settings.repository_url.clone(),
+        terms: args.terms,
+        limit: args.limit,
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<search::Parameters> {
+    let result = serde_json::from_str::<search::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &search::Result) -> String {
+    if result.packages.is_empty() {
+        return "No packages found".to_string();
+    }
+
+    let rows = result
+        .packages
+        .iter()
+        .map(|row| {
+            vec![
+                row.name.clone(),
+                row.latest_version.clone().unwrap_or("-".to_string()),
+                row.license.clone(),
+                row.description.clone(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    table(&["Name", "Latest", "License", "Description"], &rows)
+}
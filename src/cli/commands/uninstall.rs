@@ -0,0 +1,76 @@
+use crate::actions::uninstall;
+use crate::{core::settings::Settings, Result};
+
+#[derive(Debug, clap::Args)]
+pub struct UninstallArgs {
+    /// Packages to remove
+    #[arg(required = true)]
+    packages: Vec<String>,
+
+    /// Remove packages even if other installed packages depend on them
+    #[arg(long)]
+    force: bool,
+
+    /// Keep dependencies that are no longer needed by anything else
+    #[arg(long)]
+    keep_dependencies: bool,
+
+    #[arg(long)]
+    repository_path: Option<String>,
+}
+
+pub(crate) async fn do_raw(
+    params: &uninstall::Parameters,
+) -> Result<uninstall::Result, uninstall::Error> {
+    uninstall::run(params)
+}
+
+pub(crate) async fn do_cli(
+    args: UninstallArgs,
+    settings: &Settings,
+) -> Result<uninstall::Result, uninstall::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters).await
+}
+
+pub(crate) async fn make_parameters(
+    args: UninstallArgs,
+    settings: &Settings,
+) -> Result<uninstall::Parameters, uninstall::Error> {
+    let result = uninstall::Parameters {
+        repository_path: args
+            .repository_path
+            .unwrap_or(settings.global_repository_path.clone()),
+        repository_url: settings.repository_url.clone(),
+        packages: args.packages,
+        force: args.force,
+        remove_orphans: !args.keep_dependencies,
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<uninstall::Parameters> {
+    let result = serde_json::from_str::<uninstall::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &uninstall::Result) -> String {
+    if result.removed.is_empty() {
+        return "Nothing to uninstall".to_string();
+    }
+
+    result
+        .removed
+        .iter()
+        .map(|row| {
+            if row.orphan {
+                format!("Removed {} {} (no longer needed)", row.name, row.version)
+            } else {
+                format!("Removed {} {}", row.name, row.version)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
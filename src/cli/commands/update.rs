@@ -0,0 +1,128 @@
+use crate::actions::update::{self, Change};
+use crate::core::dependencies::Resolution;
+use crate::core::lockfile::project_lockfile;
+use crate::core::manifest::MANIFEST_FILE;
+use crate::core::supercollider;
+use crate::core::target::Target;
+use crate::{core::repository::Repository, core::settings::Settings, Result};
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+pub struct UpdateArgs {
+    /// Print the plan without changing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    #[arg(long)]
+    repository_path: Option<String>,
+
+    /// Lockfile to record the new versions in. Defaults to baryon.lock next to the project
+    /// manifest, if there is one.
+    #[arg(long)]
+    lockfile: Option<PathBuf>,
+
+    /// Project manifest to take repositories and overrides from. Defaults to baryon.yaml in the current
+    /// directory.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Which compatible versions to pick: highest, lowest, lowest-direct or minimal-change
+    #[arg(long, default_value_t = Resolution::Highest)]
+    resolution: Resolution,
+
+    /// Platform to resolve platform-specific dependencies for, as <os>-<arch>, e.g.
+    /// macos-aarch64. Defaults to the current platform.
+    #[arg(long)]
+    target: Option<Target>,
+}
+
+impl UpdateArgs {
+    /// The project manifest to use, whether or not it exists.
+    pub(crate) fn manifest_path(&self) -> PathBuf {
+        self.manifest
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(MANIFEST_FILE))
+    }
+}
+
+pub(crate) async fn do_raw(
+    params: &update::Parameters,
+    repo: &dyn Repository,
+) -> Result<update::Result, update::Error> {
+    update::run(params, repo).await
+}
+
+pub(crate) async fn do_cli(
+    args: UpdateArgs,
+    settings: &Settings,
+    repo: &dyn Repository,
+) -> Result<update::Result, update::Error> {
+    let parameters = make_parameters(args, settings).await?;
+    do_raw(&parameters, repo).await
+}
+
+pub(crate) async fn make_parameters(
+    args: UpdateArgs,
+    settings: &Settings,
+) -> Result<update::Parameters, update::Error> {
+    let manifest = args.manifest_path();
+    let result = update::Parameters {
+        repository_path: args
+            .repository_path
+            .unwrap_or(settings.global_repository_path.clone()),
+        repository_url: settings.repository_url.clone(),
+        dry_run: args.dry_run,
+        lockfile: args
+            .lockfile
+            .or_else(|| project_lockfile(&manifest))
+            .map(|path| path.to_string_lossy().to_string()),
+        manifest: Some(manifest.to_string_lossy().to_string()),
+        resolution: args.resolution,
+        max_steps: settings.resolve_limits.max_steps,
+        timeout_seconds: settings.resolve_limits.timeout.map(|t| t.as_secs()),
+        strict: settings.strict_repository,
+        target: args.target,
+        supercollider: settings
+            .supercollider_version
+            .clone()
+            .or_else(supercollider::detect),
+        overrides: settings.overrides.clone(),
+    };
+    Ok(result)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<update::Parameters> {
+    let result = serde_json::from_str::<update::Parameters>(json)
+        .map_err(|e| miette::Report::msg(e.to_string()))?;
+
+    Ok(result)
+}
+
+pub(crate) fn format(result: &update::Result) -> String {
+    if result.plan.changes.is_empty() {
+        return "Everything is up to date".to_string();
+    }
+
+    let mut lines = result
+        .plan
+        .changes
+        .iter()
+        .map(|change| match change {
+            Change::Install { name, version } => format!("  install   {} {}", name, version),
+            Change::Upgrade { name, from, to } => {
+                format!("  upgrade   {} {} -> {}", name, from, to)
+            }
+            Change::Downgrade { name, from, to } => {
+                format!("  downgrade {} {} -> {}", name, from, to)
+            }
+            Change::Remove { name, version } => format!("  remove    {} {}", name, version),
+        })
+        .collect::<Vec<_>>();
+
+    if result.applied {
+        lines.insert(0, "Applied:".to_string());
+    } else {
+        lines.insert(0, "Planned (dry run, nothing was changed):".to_string());
+    }
+    lines.join("\n")
+}
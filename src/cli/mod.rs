@@ -1,18 +1,33 @@
 mod commands;
 
 use crate::{
-    core::http::CacheSettings,
-    core::{repository::HTTPRepository, settings::Settings},
+    core::{config::Config, manifest, repository::CompositeRepository, settings::Settings},
     Result,
 };
 use clap::{Parser, Subcommand};
-use std::time::Duration;
+use std::path::Path;
 
-use commands::list::{self, from_json, ListArgs};
+use commands::config::{self, ConfigArgs};
+use commands::info::{self, InfoArgs};
+use commands::install::{self, InstallArgs};
+use commands::list::{self, ListArgs};
+use commands::outdated::{self, OutdatedArgs};
+use commands::repo::{self, RepoArgs};
+use commands::search::{self, SearchArgs};
+use commands::uninstall::{self, UninstallArgs};
+use commands::update::{self, UpdateArgs};
 
 #[derive(Parser)]
 #[command(version, about = "Baryon Package Manager")]
 struct Cli {
+    /// Override a configuration value for this run, e.g. `-c repository_url=...`
+    #[arg(short = 'c', long = "config", global = true, value_parser = parse_key_value)]
+    config: Vec<(String, String)>,
+
+    /// Log more: -v for progress, -vv for the resolver's reasoning, -vvv for everything
+    #[arg(short = 'v', long = "verbose", global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -20,43 +35,262 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     List(ListArgs),
-    ListRaw { json: String },
-    // Install { package: String, version: String },
-    // InstallRaw { json: String },
+    ListRaw {
+        json: String,
+    },
+    Install(InstallArgs),
+    InstallRaw {
+        json: String,
+    },
+    Uninstall(UninstallArgs),
+    UninstallRaw {
+        json: String,
+    },
+    #[command(alias = "upgrade")]
+    Update(UpdateArgs),
+    UpdateRaw {
+        json: String,
+    },
+    Outdated(OutdatedArgs),
+    OutdatedRaw {
+        json: String,
+    },
+    Search(SearchArgs),
+    SearchRaw {
+        json: String,
+    },
+    #[command(alias = "show")]
+    Info(InfoArgs),
+    InfoRaw {
+        json: String,
+    },
+    #[command(subcommand)]
+    Config(ConfigArgs),
+    ConfigRaw {
+        json: String,
+    },
+    #[command(subcommand)]
+    Repo(RepoArgs),
+    RepoRaw {
+        json: String,
+    },
 }
 
 pub(crate) async fn cli() -> Result<()> {
     let cli = Cli::parse();
-    let settings = Settings {
-        global_repository_path: "~/.baryon/repository".to_string(),
-        repository_url: "https://example.com/repo.json".to_string(),
-        cache_settings: CacheSettings {
-            cache_path: "~/.baryon/cache".to_string(),
-            cache_timeout: Duration::new(60, 0),
-        },
-    };
-    let repo = HTTPRepository::new(&settings);
+    init_logging(cli.verbose);
+    // Settings are only built for the commands that use them, so `config` and `repo` can
+    // still fix a broken value.
+    let config = Config::load(&cli.config)?;
 
-    match cli.command {
-        Commands::List(args) => list::do_cli(args, &settings, &repo)
-            .await
-            .map(|r| {
-                (serde_json::to_string_pretty(&r)
-                    .unwrap_or_else(|_| "Error serializing result".to_string()),)
-            })
-            .map_err(|e| miette::Report::msg(format!("Error: {}", e))),
+    let output = match cli.command {
+        Commands::List(args) => {
+            let settings = config.settings()?;
+            let repo = CompositeRepository::load(&settings).await?;
+            list::do_cli(args, &settings, &repo)
+                .await
+                .map(|r| list::format(&r))
+                .map_err(|e| e.base)
+        }
 
         Commands::ListRaw { json } => {
-            let obj = from_json(&json)?;
+            let settings = config.settings()?;
+            let obj = list::from_json(&json)?;
+            let repo = CompositeRepository::load(&settings).await?;
             list::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Install(args) => {
+            let mut settings = config.settings()?;
+            manifest::load_into(&args.manifest_path(), &mut settings)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            install::do_cli(args, &settings, &repo)
+                .await
+                .map(|r| install::format(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::InstallRaw { json } => {
+            let mut settings = config.settings()?;
+            let obj = install::from_json(&json)?;
+            apply_project_repositories(obj.manifest.as_deref(), &mut settings)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            install::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Uninstall(args) => {
+            let settings = config.settings()?;
+            uninstall::do_cli(args, &settings)
+                .await
+                .map(|r| uninstall::format(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::UninstallRaw { json } => {
+            let obj = uninstall::from_json(&json)?;
+            uninstall::do_raw(&obj)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Update(args) => {
+            let mut settings = config.settings()?;
+            manifest::load_into(&args.manifest_path(), &mut settings)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            update::do_cli(args, &settings, &repo)
+                .await
+                .map(|r| update::format(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::UpdateRaw { json } => {
+            let mut settings = config.settings()?;
+            let obj = update::from_json(&json)?;
+            apply_project_repositories(obj.manifest.as_deref(), &mut settings)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            update::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Outdated(args) => {
+            let settings = config.settings()?;
+            let repo = CompositeRepository::load(&settings).await?;
+            let json = args.json;
+            outdated::do_cli(args, &settings, &repo)
                 .await
                 .map(|r| {
-                    (serde_json::to_string_pretty(&r)
-                        .unwrap_or_else(|_| "Error serializing result".to_string()),)
+                    if json {
+                        to_json(&r)
+                    } else {
+                        outdated::format(&r)
+                    }
                 })
-                .map_err(|e| miette::Report::msg(format!("Error: {}", e)))
+                .map_err(|e| e.base)
+        }
+
+        Commands::OutdatedRaw { json } => {
+            let settings = config.settings()?;
+            let obj = outdated::from_json(&json)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            outdated::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Search(args) => {
+            let settings = config.settings()?;
+            let repo = CompositeRepository::load(&settings).await?;
+            search::do_cli(args, &settings, &repo)
+                .await
+                .map(|r| search::format(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::SearchRaw { json } => {
+            let settings = config.settings()?;
+            let obj = search::from_json(&json)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            search::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Info(args) => {
+            let settings = config.settings()?;
+            let repo = CompositeRepository::load(&settings).await?;
+            info::do_cli(args, &settings, &repo)
+                .await
+                .map(|r| info::format(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::InfoRaw { json } => {
+            let settings = config.settings()?;
+            let obj = info::from_json(&json)?;
+            let repo = CompositeRepository::load(&settings).await?;
+            info::do_raw(&obj, &repo)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Config(args) => config::do_cli(args, &config)
+            .await
+            .map(|r| config::format(&r))
+            .map_err(|e| e.base),
+
+        Commands::ConfigRaw { json } => {
+            let obj = config::from_json(&json)?;
+            config::do_raw(&obj, &config)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
+        }
+
+        Commands::Repo(args) => repo::do_cli(args, &config)
+            .await
+            .map(|r| repo::format(&r))
+            .map_err(|e| e.base),
+
+        Commands::RepoRaw { json } => {
+            let obj = repo::from_json(&json)?;
+            repo::do_raw(&obj, &config)
+                .await
+                .map(|r| to_json(&r))
+                .map_err(|e| e.base)
         }
     }?;
 
+    println!("{}", output);
+    Ok(())
+}
+
+fn parse_key_value(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{}`", arg))
+}
+
+/// Add the repositories of the project manifest at `path`, if there is one, to `settings`.
+/// Raw requests carry their own overrides.
+fn apply_project_repositories(path: Option<&str>, settings: &mut Settings) -> Result<()> {
+    let project = path
+        .map(|path| manifest::load(Path::new(path)))
+        .transpose()?
+        .flatten();
+    if let Some(project) = project {
+        manifest::apply_repositories(&project, settings)?;
+    }
     Ok(())
 }
+
+fn to_json<T: serde::Serialize>(result: &T) -> String {
+    serde_json::to_string_pretty(result).unwrap_or_else(|_| "Error serializing result".to_string())
+}
+
+/// Log to stderr, at a level set by the number of `-v` flags.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        2 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .init();
+}
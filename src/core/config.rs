@@ -0,0 +1,578 @@
+use crate::core::dependencies::ResolveLimits;
+use crate::core::http::CacheSettings;
+use crate::core::settings::{PackageOverride, RepositorySource, Settings};
+use crate::core::version::parse_version;
+use miette::Diagnostic;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// User config, relative to the home directory.
+pub const USER_CONFIG: &str = ".baryon/config.yaml";
+/// Project config, relative to the current directory.
+pub const PROJECT_CONFIG: &str = ".baryon/config.yaml";
+/// Prefix for environment variable overrides, e.g. `BARYON_REPOSITORY_URL`.
+pub const ENV_PREFIX: &str = "BARYON_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Path,
+    Url,
+    Seconds,
+    Count,
+    Bool,
+    Text,
+    /// Structured values, written as YAML (or JSON) when given on the command line.
+    Yaml,
+}
+
+struct Key {
+    name: &'static str,
+    default: &'static str,
+    kind: Kind,
+}
+
+const KEYS: &[Key] = &[
+    Key {
+        name: "global_repository_path",
+        default: "~/.baryon/repository",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "repository_url",
+        default: "https://example.com/repo.json",
+        kind: Kind::Url,
+    },
+    Key {
+        name: "repositories",
+        default: "[]",
+        kind: Kind::Yaml,
+    },
+    Key {
+        name: "pins",
+        default: "{}",
+        kind: Kind::Yaml,
+    },
+    Key {
+        name: "overrides",
+        default: "{}",
+        kind: Kind::Yaml,
+    },
+    Key {
+        name: "cache_path",
+        default: "~/.baryon/cache",
+        kind: Kind::Path,
+    },
+    Key {
+        name: "cache_timeout",
+        default: "60",
+        kind: Kind::Seconds,
+    },
+    // 0 means no limit for both resolver limits.
+    Key {
+        name: "resolve_max_steps",
+        default: "10000",
+        kind: Kind::Count,
+    },
+    Key {
+        name: "resolve_timeout",
+        default: "60",
+        kind: Kind::Seconds,
+    },
+    Key {
+        name: "strict_repository",
+        default: "false",
+        kind: Kind::Bool,
+    },
+    // Off, so a cloned project cannot send environment secrets to a repository it picks.
+    Key {
+        name: "expand_manifest_tokens",
+        default: "false",
+        kind: Kind::Bool,
+    },
+    // Empty means the version reported by `sclang -v`.
+    Key {
+        name: "supercollider_version",
+        default: "",
+        kind: Kind::Text,
+    },
+];
+
+/// Where a configuration value came from. Later sources override earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    Default,
+    User,
+    Project,
+    Environment,
+    CommandLine,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ConfigSource::Default => "default",
+            ConfigSource::User => "user config",
+            ConfigSource::Project => "project config",
+            ConfigSource::Environment => "environment",
+            ConfigSource::CommandLine => "command line",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub source: ConfigSource,
+    /// File path or environment variable names the values were read from.
+    pub origin: String,
+    pub values: BTreeMap<String, Value>,
+}
+
+/// An effective configuration value, with its provenance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigSource,
+    pub origin: String,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ConfigError {
+    #[error("Unknown configuration key: {key}")]
+    #[diagnostic(help("Known keys are: {}", known_keys().join(", ")))]
+    UnknownKey { key: String },
+
+    #[error("Invalid value {value:?} for {key}: {reason}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("Failed to parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+
+    #[error("Failed to access config file {path}: {source}")]
+    IO {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+pub fn known_keys() -> Vec<&'static str> {
+    KEYS.iter().map(|key| key.name).collect()
+}
+
+fn find_key(name: &str) -> Result<&'static Key, ConfigError> {
+    KEYS.iter()
+        .find(|key| key.name == name)
+        .ok_or_else(|| ConfigError::UnknownKey {
+            key: name.to_string(),
+        })
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(USER_CONFIG))
+}
+
+pub fn project_config_path() -> Option<PathBuf> {
+    std::env::current_dir()
+        .ok()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+}
+
+/// Layered configuration: defaults, user config, project config, `BARYON_*` environment
+/// variables and command line overrides, merged in that order.
+#[derive(Debug, Clone)]
+pub struct Config {
+    layers: Vec<ConfigLayer>,
+    env: HashMap<String, String>,
+    user_path: Option<PathBuf>,
+    project_path: Option<PathBuf>,
+    overrides: Vec<(String, String)>,
+}
+
+impl Config {
+    /// Load from the standard locations and the process environment.
+    pub fn load(overrides: &[(String, String)]) -> Result<Self, ConfigError> {
+        Self::from_sources(
+            user_config_path().as_deref(),
+            project_config_path().as_deref(),
+            std::env::vars().collect(),
+            overrides,
+        )
+    }
+
+    pub fn from_sources(
+        user_path: Option<&Path>,
+        project_path: Option<&Path>,
+        env: HashMap<String, String>,
+        overrides: &[(String, String)],
+    ) -> Result<Self, ConfigError> {
+        let mut layers = vec![ConfigLayer {
+            source: ConfigSource::Default,
+            origin: "built-in".to_string(),
+            values: KEYS
+                .iter()
+                .map(|key| (key.name.to_string(), parse_value(key, key.default)))
+                .collect(),
+        }];
+
+        for (source, path) in [
+            (ConfigSource::User, user_path),
+            (ConfigSource::Project, project_path),
+        ] {
+            if let Some(path) = path {
+                if path.exists() {
+                    layers.push(ConfigLayer {
+                        source,
+                        origin: path.display().to_string(),
+                        values: read_file(path)?,
+                    });
+                }
+            }
+        }
+
+        let mut env_values = BTreeMap::new();
+        let mut env_names = Vec::new();
+        for key in KEYS {
+            let var = format!("{}{}", ENV_PREFIX, key.name.to_uppercase());
+            if let Some(value) = env.get(&var) {
+                env_values.insert(key.name.to_string(), parse_value(key, value));
+                env_names.push(var);
+            }
+        }
+        layers.push(ConfigLayer {
+            source: ConfigSource::Environment,
+            origin: env_names.join(", "),
+            values: env_values,
+        });
+
+        let mut cli_values = BTreeMap::new();
+        for (name, value) in overrides {
+            let key = find_key(name)?;
+            cli_values.insert(key.name.to_string(), parse_value(key, value));
+        }
+        layers.push(ConfigLayer {
+            source: ConfigSource::CommandLine,
+            origin: "--config".to_string(),
+            values: cli_values,
+        });
+
+        Ok(Self {
+            layers,
+            env,
+            user_path: user_path.map(Path::to_path_buf),
+            project_path: project_path.map(Path::to_path_buf),
+            overrides: overrides.to_vec(),
+        })
+    }
+
+    /// Re-read every source, e.g. after a config file was changed.
+    pub fn reload(&self) -> Result<Self, ConfigError> {
+        Self::from_sources(
+            self.user_path.as_deref(),
+            self.project_path.as_deref(),
+            self.env.clone(),
+            &self.overrides,
+        )
+    }
+
+    pub fn user_path(&self) -> Option<&Path> {
+        self.user_path.as_deref()
+    }
+
+    pub fn project_path(&self) -> Option<&Path> {
+        self.project_path.as_deref()
+    }
+
+    /// The effective value of `key` and the layer it came from.
+    pub fn get(&self, key: &str) -> Result<ConfigEntry, ConfigError> {
+        let key = find_key(key)?;
+        let (value, layer) = self
+            .layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.values.get(key.name).map(|value| (value, layer)))
+            .expect("every key has a default");
+
+        let origin = match layer.source {
+            ConfigSource::Environment => format!("{}{}", ENV_PREFIX, key.name.to_uppercase()),
+            _ => layer.origin.clone(),
+        };
+        Ok(ConfigEntry {
+            key: key.name.to_string(),
+            value: display_value(value),
+            source: layer.source,
+            origin,
+        })
+    }
+
+    /// Effective values of every known key.
+    pub fn entries(&self) -> Vec<ConfigEntry> {
+        KEYS.iter()
+            .filter_map(|key| self.get(key.name).ok())
+            .collect()
+    }
+
+    pub fn settings(&self) -> Result<Settings, ConfigError> {
+        let seconds = self.get_number("cache_timeout")?;
+        let limit = |value: u64| Some(value).filter(|&value| value > 0);
+
+        let mut repositories: Vec<RepositorySource> = self.get_structured("repositories")?;
+        for repository in repositories.iter_mut() {
+            if let Some(auth) = repository.auth.as_mut() {
+                // Tokens are usually kept out of config files as `${SOME_TOKEN}`.
+                auth.token = self.expand("repositories", &auth.token)?;
+            }
+        }
+
+        Ok(Settings {
+            global_repository_path: self.expand_entry(&self.get("global_repository_path")?)?,
+            repository_url: self.get("repository_url")?.value,
+            repositories,
+            pins: self.get_structured("pins")?,
+            overrides: self.get_structured("overrides")?,
+            cache_settings: CacheSettings {
+                cache_path: self.expand_entry(&self.get("cache_path")?)?,
+                cache_timeout: Duration::from_secs(seconds),
+            },
+            resolve_limits: ResolveLimits {
+                max_steps: limit(self.get_number("resolve_max_steps")?).map(|n| n as usize),
+                timeout: limit(self.get_number("resolve_timeout")?).map(Duration::from_secs),
+            },
+            strict_repository: self.get_bool("strict_repository")?,
+            expand_manifest_tokens: self.get_bool("expand_manifest_tokens")?,
+            supercollider_version: self.get_version("supercollider_version")?,
+        })
+    }
+
+    /// The configured repositories as stored, with tokens unexpanded, or the default one built
+    /// from `repository_url`. Only those two keys are read, so other invalid values do not matter.
+    pub fn repository_sources(&self) -> Result<Vec<RepositorySource>, ConfigError> {
+        let repositories: Vec<RepositorySource> = self.get_structured("repositories")?;
+        if repositories.is_empty() {
+            Ok(vec![RepositorySource::default_for(
+                &self.get("repository_url")?.value,
+            )])
+        } else {
+            Ok(repositories)
+        }
+    }
+
+    /// Packages pinned to a repository, by package name.
+    pub fn pins(&self) -> Result<BTreeMap<String, String>, ConfigError> {
+        self.get_structured("pins")
+    }
+
+    /// The effective value of a version key, `None` if it is empty.
+    fn get_version(&self, key: &str) -> Result<Option<Version>, ConfigError> {
+        let entry = self.get(key)?;
+        if entry.value.trim().is_empty() {
+            return Ok(None);
+        }
+        parse_version(&entry.value)
+            .map(Some)
+            .map_err(|e| ConfigError::InvalidValue {
+                key: entry.key,
+                value: entry.value.clone(),
+                reason: e.reason,
+            })
+    }
+
+    /// The effective value of a boolean key.
+    fn get_bool(&self, key: &str) -> Result<bool, ConfigError> {
+        let entry = self.get(key)?;
+        entry
+            .value
+            .parse::<bool>()
+            .map_err(|e| ConfigError::InvalidValue {
+                key: entry.key,
+                value: entry.value.clone(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// The effective value of a numeric key.
+    fn get_number(&self, key: &str) -> Result<u64, ConfigError> {
+        let entry = self.get(key)?;
+        entry
+            .value
+            .parse::<u64>()
+            .map_err(|e| ConfigError::InvalidValue {
+                key: entry.key,
+                value: entry.value.clone(),
+                reason: e.to_string(),
+            })
+    }
+
+    /// The effective value of `key`, as stored, before any expansion.
+    pub fn get_value(&self, key: &str) -> Result<Value, ConfigError> {
+        let key = find_key(key)?;
+        Ok(self
+            .layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.values.get(key.name))
+            .cloned()
+            .unwrap_or(Value::Null))
+    }
+
+    /// The effective value of a structured key, deserialized.
+    fn get_structured<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<T, ConfigError> {
+        let value = self.get_value(key)?;
+        serde_yaml::from_value(value.clone()).map_err(|e| ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: display_value(&value),
+            reason: e.to_string(),
+        })
+    }
+
+    fn expand_entry(&self, entry: &ConfigEntry) -> Result<String, ConfigError> {
+        self.expand(&entry.key, &entry.value)
+    }
+
+    /// Expand `~` and `$VAR`/`${VAR}` in a value.
+    fn expand(&self, key: &str, value: &str) -> Result<String, ConfigError> {
+        shellexpand::full_with_context(
+            value,
+            || dirs::home_dir().map(|home| home.to_string_lossy().to_string()),
+            |var: &str| Ok::<_, std::env::VarError>(self.env.get(var).cloned()),
+        )
+        .map(|expanded| expanded.to_string())
+        .map_err(|e| ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Write `key: value` into the config file at `path`, keeping any other values in it.
+pub fn set_value(path: &Path, key: &str, value: &str) -> Result<(), ConfigError> {
+    let key = find_key(key)?;
+    let parsed = parse_value(key, value);
+    check_value(key, &parsed).map_err(|reason| ConfigError::InvalidValue {
+        key: key.name.to_string(),
+        value: value.to_string(),
+        reason,
+    })?;
+    edit_file(path, |values| {
+        values.insert(key.name.to_string(), parsed);
+        Ok::<_, ConfigError>(())
+    })
+}
+
+/// Read the config file at `path` (empty if missing), change it, and write it back.
+/// Nothing is written if `edit` fails.
+pub fn edit_file<E: From<ConfigError>>(
+    path: &Path,
+    edit: impl FnOnce(&mut BTreeMap<String, Value>) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut values = if path.exists() {
+        read_file(path)?
+    } else {
+        BTreeMap::new()
+    };
+    edit(&mut values)?;
+    Ok(write_file(path, &values)?)
+}
+
+fn read_file(path: &Path) -> Result<BTreeMap<String, Value>, ConfigError> {
+    let data = std::fs::read_to_string(path).map_err(|source| ConfigError::IO {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if data.trim().is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    serde_yaml::from_str(&data).map_err(|source| ConfigError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn write_file(path: &Path, values: &BTreeMap<String, Value>) -> Result<(), ConfigError> {
+    let data = serde_yaml::to_string(values).map_err(|source| ConfigError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|source| ConfigError::IO {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    std::fs::write(path, data).map_err(|source| ConfigError::IO {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_value(key: &Key, value: &str) -> Value {
+    match key.kind {
+        Kind::Seconds | Kind::Count => value
+            .trim()
+            .parse::<u64>()
+            .map(Value::from)
+            .unwrap_or(Value::String(value.to_string())),
+        Kind::Bool => value
+            .trim()
+            .parse::<bool>()
+            .map(Value::from)
+            .unwrap_or(Value::String(value.to_string())),
+        Kind::Path | Kind::Url | Kind::Text => Value::String(value.to_string()),
+        Kind::Yaml => serde_yaml::from_str(value).unwrap_or(Value::String(value.to_string())),
+    }
+}
+
+/// Whether `value` is what `Config::settings` expects of `key`, so a value that would break
+/// every later command is not written in the first place.
+fn check_value(key: &Key, value: &Value) -> Result<(), String> {
+    fn shape<T: serde::de::DeserializeOwned>(value: &Value) -> Result<(), String> {
+        serde_yaml::from_value::<T>(value.clone())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    match (key.kind, key.name) {
+        (Kind::Seconds | Kind::Count, _) => value
+            .as_u64()
+            .map(|_| ())
+            .ok_or_else(|| "expected a whole number".to_string()),
+        (Kind::Bool, _) => value
+            .as_bool()
+            .map(|_| ())
+            .ok_or_else(|| "expected true or false".to_string()),
+        (Kind::Yaml, "repositories") => shape::<Vec<RepositorySource>>(value),
+        (Kind::Yaml, "pins") => shape::<BTreeMap<String, String>>(value),
+        (Kind::Yaml, "overrides") => shape::<BTreeMap<String, PackageOverride>>(value),
+        (Kind::Text, "supercollider_version") => match value.as_str() {
+            Some(version) if !version.trim().is_empty() => {
+                parse_version(version).map(|_| ()).map_err(|e| e.reason)
+            }
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::new(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
@@ -1,13 +1,25 @@
-use crate::specs::Package;
-use semver::Version;
-use std::collections::HashMap;
-use std::time::Instant;
+use super::installed::check_directory_name;
+use super::repository::Repository as PackageSource;
+use super::solver::{Fact, Failure, IncompatibilityId, Solver};
+use super::supercollider;
+use super::target::{Condition, Target};
+use crate::specs::Dependency;
+use miette::Diagnostic;
+use semver::{BuildMetadata, Comparator, Op, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub struct PackageVersion {
     pub name: String,
     pub version: Version,
     pub required_by: Vec<PackageRequirement>, // Names of packages that require this one
+    /// Features enabled because some requirement on the package asked for them.
+    pub features: Vec<String>,
 }
 
 impl PackageVersion {
@@ -16,61 +28,530 @@ impl PackageVersion {
             name: name.to_string(),
             version: Version::parse(version_str)?,
             required_by: Vec::new(),
+            features: Vec::new(),
         })
     }
 }
 
-use semver::VersionReq;
+use super::version::{parse_version, VersionSpec, VERSION_RULES};
 
 #[derive(Debug, Clone)]
 pub struct PackageRequirement {
     pub name: String,
-    pub spec: VersionReq,
+    pub spec: VersionSpec,
     pub required_by: Vec<PackageRequirement>, // Names of packages that require this one
+    /// Platforms the requirement applies to; `None` for all of them.
+    pub condition: Option<Condition>,
 }
 
 impl PackageRequirement {
     pub fn new(name: String, spec_str: String) -> Result<Self, semver::Error> {
         Ok(Self {
             name: name.to_string(),
-            spec: VersionReq::parse(&spec_str)?,
+            spec: VersionSpec::parse(&spec_str)?,
             required_by: Vec::new(),
+            condition: None,
         })
     }
 
+    /// The requirements behind a dependency that may name features, as in `package1[gui,midi]`:
+    /// one on the package itself, then one on each feature.
+    pub fn with_features(name: String, spec_str: String) -> Result<Vec<Self>, semver::Error> {
+        let spec = VersionSpec::parse(&spec_str)?;
+        let (package, features) = split_features(&name);
+        let mut requirements = vec![Self {
+            name: package.to_string(),
+            spec: spec.clone(),
+            required_by: Vec::new(),
+            condition: None,
+        }];
+        for feature in features {
+            requirements.push(Self {
+                name: feature_package(package, feature),
+                spec: spec.clone(),
+                required_by: Vec::new(),
+                condition: None,
+            });
+        }
+        Ok(requirements)
+    }
+
     pub fn matches(&self, version: &Version) -> bool {
         self.spec.matches(version)
     }
 }
 
-#[derive(Debug)]
+/// Split a dependency name like `package1[gui, midi]` into the package and its features.
+pub fn split_features(name: &str) -> (&str, Vec<&str>) {
+    match name.split_once('[') {
+        Some((package, rest)) => {
+            let features = rest
+                .trim_end_matches(']')
+                .split(',')
+                .map(str::trim)
+                .filter(|feature| !feature.is_empty())
+                .collect();
+            (package, features)
+        }
+        None => (name, Vec::new()),
+    }
+}
+
+/// Name of the package standing for `feature` of `package` while resolving. Its versions are
+/// those of the package, and each depends on the same version of the package plus the optional
+/// dependencies of the feature, so features asked for by different requirers are unified.
+fn feature_package(package: &str, feature: &str) -> String {
+    format!("{}[{}]", package, feature)
+}
+
+/// The platforms an index dependency is limited to; `None` for all of them.
+pub fn dependency_condition(dependency: &Dependency) -> Option<Condition> {
+    match dependency {
+        Dependency::Requirement(_) => None,
+        Dependency::TargetedRequirement(targeted) => Some(Condition {
+            os: targeted.os.clone(),
+            arch: targeted.arch.clone(),
+        }),
+    }
+}
+
+/// The requirements behind an index dependency, which only apply on the platforms it names.
+fn parse_dependency(
+    name: &str,
+    dependency: &Dependency,
+) -> Result<Vec<PackageRequirement>, semver::Error> {
+    let condition = dependency_condition(dependency);
+    let mut requirements =
+        PackageRequirement::with_features(name.to_string(), dependency.requirement().to_string())?;
+    for requirement in requirements.iter_mut() {
+        requirement.condition = condition.clone();
+    }
+    Ok(requirements)
+}
+
+/// The version under which `provider` at `version` stands in for another package at
+/// `stands_for`. Build metadata naming the provider keeps it apart from the package's own
+/// releases and from other providers, while requirements on the package still match it:
+/// `1.5.0+via.newquark.2.0.0`.
+pub fn provided_version(stands_for: &Version, provider: &str, version: &Version) -> Version {
+    let provider = provider
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    let version = Version {
+        build: BuildMetadata::EMPTY,
+        ..version.clone()
+    };
+    Version {
+        build: BuildMetadata::new(&format!("via.{}.{}", provider, version))
+            .unwrap_or(BuildMetadata::EMPTY),
+        ..stands_for.clone()
+    }
+}
+
+/// A requirement on exactly `version`.
+fn exact(version: &Version) -> VersionSpec {
+    VersionReq {
+        comparators: vec![Comparator {
+            op: Op::Exact,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }],
+    }
+    .into()
+}
+
+#[derive(Debug, Clone)]
 pub struct Repository {
     pub data: HashMap<String, HashMap<Version, Vec<PackageRequirement>>>,
+    /// Versions as written in the index, by package, where they are not valid semver.
+    pub original: HashMap<String, HashMap<Version, String>>,
+    /// Yanked versions by package, with the reason if one was given.
+    pub yanked: HashMap<String, HashMap<Version, Option<String>>>,
+    /// Releases left out because they could not be parsed.
+    pub skipped: Vec<InvalidRelease>,
+    /// Packages each release cannot be installed alongside, by package and version.
+    pub conflicts: HashMap<String, HashMap<Version, Vec<PackageRequirement>>>,
+    /// Versions that stand for another package's release, which provides or replaces the
+    /// package, by package and version. See [`provided_version`].
+    pub provided: HashMap<String, HashMap<Version, (String, Version)>>,
+}
+
+/// A release field that could not be parsed.
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[error("{package} {release}: invalid {field} {value:?}: {reason}")]
+pub struct InvalidRelease {
+    pub package: String,
+    /// The release's version, as written in the index.
+    pub release: String,
+    /// `version`, `dependencies.<name>` for a dependency requirement, or the field that does not
+    /// match the index schema.
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+    #[help]
+    pub help: Option<String>,
+}
+
+/// Every invalid release in a package index.
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[error("The package index has {} invalid releases", releases.len())]
+#[diagnostic(
+    code(baryon::index::invalid),
+    help("Set strict_repository to false to skip invalid releases with a warning instead.")
+)]
+pub struct InvalidIndex {
+    #[related]
+    pub releases: Vec<InvalidRelease>,
 }
 
 impl Repository {
-    pub fn new(repo: Vec<&Package>) -> Self {
+    /// Index the releases of `repo`. Releases that do not match the index schema, belong to a
+    /// package whose name is not a plain directory name, have an invalid version or dependency
+    /// requirement, or whose versions read as the same one, are all reported together if
+    /// `strict`, and otherwise skipped with a warning each.
+    pub fn new(source: &dyn PackageSource, strict: bool) -> Result<Self, InvalidIndex> {
+        let repo = source.get_packages();
         let mut data = HashMap::new();
+        let mut original = HashMap::new();
+        let mut yanked: HashMap<String, HashMap<Version, Option<String>>> = HashMap::new();
+        let mut conflicts: HashMap<String, HashMap<Version, Vec<PackageRequirement>>> =
+            HashMap::new();
+        let mut provided: HashMap<String, HashMap<Version, (String, Version)>> = HashMap::new();
+        let mut invalid = source
+            .invalid_releases()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
         for package in repo.iter() {
             let mut releases = HashMap::new();
-            for item in package.releases.iter() {
-                let version = Version::parse(&item.version).unwrap();
-                let dependencies = item
-                    .dependencies
-                    .iter()
-                    .map(|dep| {
-                        PackageRequirement::new(dep.0.to_string(), dep.1.to_string()).unwrap()
+            let mut originals = HashMap::new();
+            let mut feature_releases = HashMap::new();
+            // Releases by the version they read as, to catch legacy versions that collide.
+            let mut readings: HashMap<Version, Vec<usize>> = HashMap::new();
+            for (index, item) in package.releases.iter().enumerate() {
+                if let Ok(version) = parse_version(&item.version) {
+                    readings.entry(version).or_default().push(index);
+                }
+            }
+            for (index, item) in package.releases.iter().enumerate() {
+                let problem = |field: String, value: &str, reason: String| InvalidRelease {
+                    package: package.name.clone(),
+                    release: item.version.to_string(),
+                    field,
+                    value: value.to_string(),
+                    reason,
+                    help: None,
+                };
+
+                let version = parse_version(&item.version)
+                    .map_err(|e| e.reason)
+                    .and_then(|version| {
+                        let others = readings[&version]
+                            .iter()
+                            .filter(|other| **other != index)
+                            .map(|other| format!("{:?}", package.releases[*other].version.as_str()))
+                            .collect::<Vec<_>>();
+                        match others.is_empty() {
+                            true => Ok(version),
+                            false => Err(format!(
+                                "reads as {}, as does {}",
+                                version,
+                                others.join(", ")
+                            )),
+                        }
                     })
-                    .collect::<Vec<_>>();
+                    .map_err(|reason| InvalidRelease {
+                        help: Some(VERSION_RULES.to_string()),
+                        ..problem("version".to_string(), &item.version, reason)
+                    });
+                let mut dependencies = Vec::new();
+                let mut problems = Vec::new();
+                // The name becomes a directory in the repository path once installed.
+                if let Err(reason) = check_directory_name(&package.name) {
+                    problems.push(problem("name".to_string(), &package.name, reason));
+                }
+                let mut names = item.dependencies.iter().collect::<Vec<_>>();
+                names.sort_by_key(|(name, _)| name.to_string());
+                for (name, spec) in names {
+                    match parse_dependency(name, spec) {
+                        Ok(requirements) => dependencies.extend(requirements),
+                        Err(e) => problems.push(problem(
+                            format!("dependencies.{}", name.as_str()),
+                            spec.requirement(),
+                            e.to_string(),
+                        )),
+                    }
+                }
+                if let Some(spec) = &item.supercollider {
+                    match VersionSpec::parse(spec) {
+                        Ok(spec) => dependencies.push(PackageRequirement {
+                            name: supercollider::PACKAGE.to_string(),
+                            spec,
+                            required_by: Vec::new(),
+                            condition: None,
+                        }),
+                        Err(e) => {
+                            problems.push(problem("supercollider".to_string(), spec, e.to_string()))
+                        }
+                    }
+                }
+                let mut conflicting = Vec::new();
+                for (name, spec) in item.conflicts.iter() {
+                    match PackageRequirement::new(name.to_string(), spec.to_string()) {
+                        Ok(requirement) => conflicting.push(requirement),
+                        Err(e) => problems.push(problem(
+                            format!("conflicts.{}", name.as_str()),
+                            spec,
+                            e.to_string(),
+                        )),
+                    }
+                }
+                // A release conflicts with the packages it replaces, besides standing in for them.
+                let mut stands_in = Vec::new();
+                let provides = item
+                    .provides
+                    .iter()
+                    .map(|(name, version)| (name.as_str(), version.as_str(), false));
+                let replaces = item
+                    .replaces
+                    .iter()
+                    .map(|(name, version)| (name.as_str(), version.as_str(), true));
+                for (name, version, replaces) in provides.chain(replaces) {
+                    let field = if replaces { "replaces" } else { "provides" };
+                    match parse_version(version) {
+                        Ok(version) => stands_in.push((name.to_string(), version)),
+                        Err(e) => {
+                            problems.push(problem(format!("{}.{}", field, name), version, e.reason))
+                        }
+                    }
+                    if replaces {
+                        conflicting.push(PackageRequirement {
+                            name: name.to_string(),
+                            spec: VersionReq::STAR.into(),
+                            required_by: Vec::new(),
+                            condition: None,
+                        });
+                    }
+                }
+                let mut features = Vec::new();
+                for (feature, optional) in item.features.iter() {
+                    let mut names = optional.iter().collect::<Vec<_>>();
+                    names.sort_by_key(|(name, _)| name.to_string());
+                    let mut requirements = Vec::new();
+                    for (name, spec) in names {
+                        match parse_dependency(name, spec) {
+                            Ok(parsed) => requirements.extend(parsed),
+                            Err(e) => problems.push(problem(
+                                format!("features.{}.{}", feature.as_str(), name.as_str()),
+                                spec.requirement(),
+                                e.to_string(),
+                            )),
+                        }
+                    }
+                    features.push((feature.to_string(), requirements));
+                }
 
-                releases.insert(version, dependencies);
+                match version {
+                    Ok(version) if problems.is_empty() => {
+                        if version.to_string() != *item.version {
+                            originals.insert(version.clone(), item.version.to_string());
+                        }
+                        if item.yanked {
+                            yanked
+                                .entry(package.name.clone())
+                                .or_default()
+                                .insert(version.clone(), item.yanked_reason.clone());
+                        }
+                        for (feature, requirements) in features {
+                            let mut dependencies = vec![PackageRequirement {
+                                name: package.name.to_string(),
+                                spec: exact(&version),
+                                required_by: Vec::new(),
+                                condition: None,
+                            }];
+                            dependencies.extend(requirements);
+                            feature_releases
+                                .entry(feature_package(&package.name, &feature))
+                                .or_insert_with(HashMap::new)
+                                .insert(version.clone(), dependencies);
+                        }
+                        for (name, stands_for) in stands_in {
+                            let dependency = PackageRequirement {
+                                name: package.name.to_string(),
+                                spec: exact(&version),
+                                required_by: Vec::new(),
+                                condition: None,
+                            };
+                            let stands_for = provided_version(&stands_for, &package.name, &version);
+                            provided.entry(name.clone()).or_default().insert(
+                                stands_for.clone(),
+                                (package.name.to_string(), version.clone()),
+                            );
+                            feature_releases
+                                .entry(name)
+                                .or_insert_with(HashMap::new)
+                                .insert(stands_for, vec![dependency]);
+                        }
+                        if !conflicting.is_empty() {
+                            conflicts
+                                .entry(package.name.to_string())
+                                .or_default()
+                                .insert(version.clone(), conflicting);
+                        }
+                        releases.insert(version, dependencies);
+                    }
+                    Ok(_) => invalid.extend(problems),
+                    Err(problem) => {
+                        invalid.push(problem);
+                        invalid.extend(problems);
+                    }
+                }
+            }
+            data.entry(package.name.clone())
+                .or_insert_with(HashMap::new)
+                .extend(releases);
+            for (name, releases) in feature_releases {
+                data.entry(name)
+                    .or_insert_with(HashMap::new)
+                    .extend(releases);
+            }
+            if !originals.is_empty() {
+                original.insert(package.name.clone(), originals);
+            }
+        }
+
+        if strict && !invalid.is_empty() {
+            return Err(InvalidIndex { releases: invalid });
+        }
+        for release in invalid.iter() {
+            tracing::warn!("skipping {}", release);
+        }
+        Ok(Self {
+            data,
+            original,
+            yanked,
+            skipped: invalid,
+            conflicts,
+            provided,
+        })
+    }
+
+    /// The index as seen from `target`: dependencies conditional on other platforms are dropped.
+    pub fn for_target(&self, target: &Target) -> Self {
+        let mut repo = self.clone();
+        for releases in repo.data.values_mut() {
+            for dependencies in releases.values_mut() {
+                dependencies.retain(|requirement| {
+                    requirement
+                        .condition
+                        .as_ref()
+                        .is_none_or(|condition| condition.matches(target))
+                });
+            }
+        }
+        repo
+    }
+
+    /// Only offer the `forced` version of each package named there, and of its feature
+    /// packages, and let it meet every requirement on them. A forced version missing from the
+    /// index has no dependencies, and its features none besides the package itself.
+    pub fn with_forced_versions(mut self, forced: &HashMap<String, Version>) -> Self {
+        for (package, version) in forced {
+            let features = self
+                .data
+                .keys()
+                .filter(|name| {
+                    let (base, features) = split_features(name);
+                    base == package && !features.is_empty()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for name in features.into_iter().chain([package.clone()]) {
+                let dependencies = self
+                    .data
+                    .get(&name)
+                    .and_then(|releases| releases.get(version))
+                    .cloned()
+                    .unwrap_or_else(|| match name == *package {
+                        true => Vec::new(),
+                        false => vec![PackageRequirement {
+                            name: package.clone(),
+                            spec: exact(version),
+                            required_by: Vec::new(),
+                            condition: None,
+                        }],
+                    });
+                self.data
+                    .insert(name, HashMap::from([(version.clone(), dependencies)]));
+            }
+        }
+        for releases in self.data.values_mut() {
+            for dependencies in releases.values_mut() {
+                for requirement in dependencies.iter_mut() {
+                    if let Some(version) = forced.get(split_features(&requirement.name).0) {
+                        requirement.spec = exact(version);
+                    }
+                }
             }
-            data.insert(package.name.clone(), releases);
         }
-        Self { data }
+        self
+    }
+
+    /// Fix SuperCollider to `version`. Without a known version, releases are assumed to work
+    /// with whichever SuperCollider is used.
+    pub fn with_supercollider(mut self, version: Option<&Version>) -> Self {
+        match version {
+            Some(version) => {
+                let releases = HashMap::from([(version.clone(), Vec::new())]);
+                self.data
+                    .insert(supercollider::PACKAGE.to_string(), releases);
+            }
+            None => {
+                for releases in self.data.values_mut() {
+                    for dependencies in releases.values_mut() {
+                        dependencies
+                            .retain(|requirement| requirement.name != supercollider::PACKAGE);
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// The release that `version` of `package` stands for, if it is provided by another package.
+    pub fn provider(&self, package: &str, version: &Version) -> Option<&(String, Version)> {
+        self.provided
+            .get(package)
+            .and_then(|versions| versions.get(version))
     }
 
-    fn get_versions(&self, package: &str) -> Vec<PackageVersion> {
+    pub fn is_yanked(&self, package: &str, version: &Version) -> bool {
+        self.yanked
+            .get(package)
+            .is_some_and(|versions| versions.contains_key(version))
+    }
+
+    /// Why `version` of `package` was yanked, if it was and a reason was given.
+    pub fn yank_reason(&self, package: &str, version: &Version) -> Option<&str> {
+        self.yanked
+            .get(package)
+            .and_then(|versions| versions.get(version))
+            .and_then(|reason| reason.as_deref())
+    }
+
+    /// `version` of `package` as the index writes it, e.g. `1.0beta` for `1.0.0-beta`.
+    pub fn display_version(&self, package: &str, version: &Version) -> String {
+        self.original
+            .get(package)
+            .and_then(|versions| versions.get(version))
+            .cloned()
+            .unwrap_or_else(|| version.to_string())
+    }
+
+    pub fn get_versions(&self, package: &str) -> Vec<PackageVersion> {
         self.data
             .get(package)
             .map(|versions| {
@@ -81,6 +562,7 @@ impl Repository {
                         name: package.to_string(),
                         version: v,
                         required_by: Vec::new(),
+                        features: Vec::new(),
                     })
                     .collect()
             })
@@ -96,30 +578,98 @@ impl Repository {
     }
 }
 
-#[derive(Debug)]
+/// How the resolver picks among the versions that satisfy every requirement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolution {
+    /// The newest compatible version of every package.
+    #[default]
+    Highest,
+    /// The oldest compatible version of every package, to check that lower bounds hold.
+    Lowest,
+    /// The oldest compatible version of the requested packages, and the newest of their
+    /// dependencies.
+    LowestDirect,
+    /// The current version of every package that has one (see [`Strategy::current`]) while it
+    /// is still compatible, else the closest newer version, else the newest.
+    MinimalChange,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Resolution::Highest => "highest",
+            Resolution::Lowest => "lowest",
+            Resolution::LowestDirect => "lowest-direct",
+            Resolution::MinimalChange => "minimal-change",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "highest" => Ok(Resolution::Highest),
+            "lowest" => Ok(Resolution::Lowest),
+            "lowest-direct" => Ok(Resolution::LowestDirect),
+            "minimal-change" => Ok(Resolution::MinimalChange),
+            _ => Err(format!(
+                "unknown resolution {:?}, expected one of highest, lowest, lowest-direct, minimal-change",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Strategy {
-    conservative: bool,
-    avoid_prerelease: bool,
+    pub resolution: Resolution,
+    pub avoid_prerelease: bool,
+    /// Versions [`Resolution::MinimalChange`] tries to keep, e.g. what is installed or locked.
+    pub current: HashMap<String, Version>,
 }
 
 impl Default for Strategy {
     fn default() -> Self {
-        Self {
-            conservative: true,
-            avoid_prerelease: false,
-        }
+        Self::new()
     }
 }
 
 impl Strategy {
+    /// Prefer the oldest compatible version of each package.
     pub fn new() -> Self {
+        Self::with_resolution(Resolution::Lowest)
+    }
+
+    /// Prefer the newest compatible version of each package.
+    pub fn latest() -> Self {
+        Self::with_resolution(Resolution::Highest)
+    }
+
+    pub fn with_resolution(resolution: Resolution) -> Self {
         Self {
-            conservative: true,
+            resolution,
             avoid_prerelease: false,
+            current: HashMap::new(),
         }
     }
 
-    fn filter_versions(&self, versions: Vec<PackageVersion>) -> Vec<PackageVersion> {
+    /// Whether prerelease versions should be skipped.
+    pub fn avoid_prerelease(mut self, avoid_prerelease: bool) -> Self {
+        self.avoid_prerelease = avoid_prerelease;
+        self
+    }
+
+    /// The versions to stay close to under [`Resolution::MinimalChange`].
+    pub fn current(mut self, versions: HashMap<String, Version>) -> Self {
+        self.current = versions;
+        self
+    }
+
+    pub fn filter_versions(&self, versions: Vec<PackageVersion>) -> Vec<PackageVersion> {
         versions
             .into_iter()
             .filter(|v| {
@@ -132,40 +682,288 @@ impl Strategy {
             .collect()
     }
 
-    fn sort_versions(&self, mut versions: Vec<PackageVersion>) -> Vec<PackageVersion> {
-        if self.conservative {
-            versions.sort_by(|a, b| b.version.cmp(&a.version));
+    /// Pick one of `candidates` (sorted ascending) for `package`, which was requested directly
+    /// if `direct`, or `None` if the strategy rules them all out.
+    pub(crate) fn choose<'v>(
+        &self,
+        package: &str,
+        direct: bool,
+        candidates: &'v [Version],
+    ) -> Option<&'v Version> {
+        let mut allowed = candidates
+            .iter()
+            .filter(|v| !self.avoid_prerelease || v.pre.is_empty());
+        match self.resolution {
+            Resolution::Highest => allowed.next_back(),
+            Resolution::Lowest => allowed.next(),
+            Resolution::LowestDirect if direct => allowed.next(),
+            Resolution::LowestDirect => allowed.next_back(),
+            Resolution::MinimalChange => {
+                let allowed = allowed.collect::<Vec<_>>();
+                match self.current.get(package) {
+                    Some(current) => allowed
+                        .iter()
+                        .find(|v| **v >= current)
+                        .or(allowed.last())
+                        .copied(),
+                    None => allowed.last().copied(),
+                }
+            }
+        }
+    }
+}
+
+/// A package in a requirement chain, and the versions of it that place the requirement.
+#[derive(Debug, Clone)]
+pub struct Requirer {
+    pub name: String,
+    /// A single version, a range, or `*` for every version.
+    pub versions: String,
+}
+
+impl fmt::Display for Requirer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.versions == "*" {
+            write!(f, "{}", self.name)
         } else {
-            versions.sort_by(|a, b| a.version.cmp(&b.version));
+            write!(f, "{} {}", self.name, self.versions)
         }
-        versions
     }
 }
 
+/// One of the requirements involved in a conflict.
 #[derive(Debug, Clone)]
-struct State {
-    requirements: Vec<PackageRequirement>,
-    selected: HashMap<String, PackageVersion>,
-    current_requirement: PackageRequirement,
-    possibilities: Vec<PackageVersion>,
-    depth: usize,
-    name: String,
+pub struct ConflictingRequirement {
+    pub name: String,
+    pub spec: VersionSpec,
+    /// The packages that led to this requirement, from the requested package down to the
+    /// one that placed it. Empty if the requirement was requested directly.
+    pub chain: Vec<Requirer>,
+    /// Whether the last package in `chain` conflicts with `name` matching `spec`, rather than
+    /// requiring it.
+    pub conflict: bool,
+}
+
+impl fmt::Display for ConflictingRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = supercollider::display_name(&self.name);
+        match self.chain.last() {
+            Some(parent) if self.conflict => {
+                write!(f, "{} conflicts with {} {}", parent, name, self.spec)
+            }
+            Some(parent) => write!(f, "{} requires {} {}", parent, name, self.spec),
+            None => write!(f, "{} {} was requested", name, self.spec),
+        }
+    }
 }
 
-pub struct FailedRequirement {
-    pub desc: String,
+/// Why resolution failed: the requirements on `package` that could not all be met.
+#[derive(Debug, Clone)]
+pub struct ConflictReport {
+    pub package: String,
+    pub requirements: Vec<ConflictingRequirement>,
+    /// Versions of `package` that were selected at some point during the search.
+    pub tried: Vec<Version>,
+    /// Every version of `package` in the repository.
+    pub available: Vec<Version>,
+    /// How the conflict follows from the requirements, one step per line.
+    pub explanation: Vec<String>,
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements = self
+            .requirements
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+        match requirements.as_slice() {
+            [] => write!(f, "Unable to resolve {}", self.package),
+            _ if self.package == supercollider::PACKAGE => write!(
+                f,
+                "{}, but SuperCollider is {}",
+                requirements.join(", and "),
+                join_versions(&self.available)
+            ),
+            [only] if self.available.is_empty() => {
+                write!(f, "{}, but no package named {} exists", only, self.package)
+            }
+            [only] => write!(f, "{}, but no version of {} matches", only, self.package),
+            [first, rest @ ..] => write!(f, "{}, but {}", first, rest.join(", and ")),
+        }
+    }
+}
+
+impl std::error::Error for ConflictReport {}
+
+impl Diagnostic for ConflictReport {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("baryon::resolve::conflict"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let mut lines = self.explanation.clone();
+        for requirement in self.requirements.iter().filter(|r| r.chain.len() > 1) {
+            let chain = requirement
+                .chain
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<_>>();
+            lines.push(format!(
+                "{} {} is {} through {}",
+                supercollider::display_name(&requirement.name),
+                requirement.spec,
+                if requirement.conflict {
+                    "ruled out"
+                } else {
+                    "required"
+                },
+                chain.join(" -> ")
+            ));
+        }
+        if !self.tried.is_empty() {
+            lines.push(format!(
+                "Tried {} {}",
+                self.package,
+                join_versions(&self.tried)
+            ));
+        }
+        if self.package == supercollider::PACKAGE {
+            lines.push(
+                "The SuperCollider version comes from the supercollider_version setting, or \
+                 else from sclang -v"
+                    .to_string(),
+            );
+        } else if !self.available.is_empty() {
+            lines.push(format!(
+                "Available versions of {}: {}",
+                self.package,
+                join_versions(&self.available)
+            ));
+        }
+        if lines.is_empty() {
+            None
+        } else {
+            Some(Box::new(lines.join("\n")))
+        }
+    }
+}
+
+fn join_versions(versions: &[Version]) -> String {
+    versions
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What packages are resolved for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    pub target: Target,
+    /// `None` if the SuperCollider version is not known, in which case it is not checked.
+    pub supercollider: Option<Version>,
+}
+
+/// How long the resolver may search before giving up. `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolveLimits {
+    /// Backjumps, i.e. how often the solver may undo decisions after a conflict.
+    pub max_steps: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+/// The limit a resolution went over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    /// The number of steps taken.
+    Steps(usize),
+    /// The time spent.
+    Timeout(Duration),
+}
+
+/// Resolution gave up before finding an answer or proving there is none.
+#[derive(Debug, Clone)]
+pub struct ResolveAborted {
+    pub reason: AbortReason,
+    pub steps: usize,
+    /// The packages that took part in the most conflicts, with how many, most first.
+    pub contended: Vec<(String, usize)>,
+}
+
+impl fmt::Display for ResolveAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            AbortReason::Steps(steps) => {
+                write!(f, "Gave up resolving after {} backtracking steps", steps)
+            }
+            AbortReason::Timeout(elapsed) => write!(
+                f,
+                "Gave up resolving after {:.1}s ({} backtracking steps)",
+                elapsed.as_secs_f64(),
+                self.steps
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolveAborted {}
+
+impl Diagnostic for ResolveAborted {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("baryon::resolve::aborted"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let mut lines = Vec::new();
+        if !self.contended.is_empty() {
+            let contended = self
+                .contended
+                .iter()
+                .map(|(name, conflicts)| format!("{} ({} conflicts)", name, conflicts))
+                .collect::<Vec<_>>();
+            lines.push(format!("Most contended packages: {}", contended.join(", ")));
+        }
+        lines.push(
+            "Narrow the requirements on these packages, or raise resolve_max_steps or \
+             resolve_timeout."
+                .to_string(),
+        );
+        Some(Box::new(lines.join("\n")))
+    }
+}
+
+/// Why [`PackageResolver::resolve`] failed.
+#[derive(Debug, Error, Diagnostic)]
+pub enum ResolveError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Conflict(#[from] ConflictReport),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Aborted(#[from] ResolveAborted),
+}
+
+/// How versions recorded in a lockfile constrain resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locking {
+    /// Pick the locked version of a package whenever it is still allowed.
+    Prefer,
+    /// Never pick anything but the locked version of a package.
+    Require,
 }
 
 pub struct PackageResolver {
-    initial_requirements: Vec<PackageRequirement>,
+    requirements: Vec<PackageRequirement>,
     repo: Repository,
     strategy: Strategy,
-    states: Vec<State>,
+    locked: HashMap<String, Version>,
+    locking: Option<Locking>,
+    limits: ResolveLimits,
+    target: Target,
+    supercollider: Option<Version>,
     selected: HashMap<String, PackageVersion>,
-    requirements: Vec<PackageRequirement>,
-    errors: HashMap<String, (Option<semver::Version>, semver::VersionReq)>,
-    depth: usize,
-    start_time: Instant,
 }
 
 impl PackageResolver {
@@ -175,221 +973,257 @@ impl PackageResolver {
         strategy: Strategy,
     ) -> Self {
         PackageResolver {
-            initial_requirements: requirements.clone(),
+            requirements,
             repo,
             strategy,
-            states: Vec::new(),
+            locked: HashMap::new(),
+            locking: None,
+            limits: ResolveLimits::default(),
+            target: Target::current(),
+            supercollider: None,
             selected: HashMap::new(),
-            requirements,
-            errors: HashMap::new(),
-            depth: 0,
-            start_time: Instant::now(),
         }
     }
 
-    pub fn resolve(&mut self) -> Result<&HashMap<String, PackageVersion>, FailedRequirement> {
-        while let Some(current_req) = self.requirements.pop() {
-            // We've already selected a version for this package
-            let existing = self.selected.get(&current_req.name);
-            if let Some(existing_version) = existing {
-                if current_req.matches(&existing_version.version) {
-                    // %s compatible with current version %s
-                    self.errors.remove(&current_req.name);
-                } else {
-                    // %s conflicts with existing version %s
-                    self.errors.insert(
-                        current_req.name.clone(),
-                        (
-                            Some(existing_version.version.clone()),
-                            current_req.spec.clone(),
-                        ),
-                    );
-
-                    let parent = current_req.required_by.last().cloned();
-                    let parent_state = self.find_state(parent.as_ref());
-                    let conflict_parent =
-                        if parent_state.is_some_and(|s| !s.possibilities.is_empty()) {
-                            // parent of current requirement has other possibilities, so try this
-                            self.find_conflict_parent(&current_req, None)
-                        } else {
-                            // try to handle by stepping back both the current requirement and the existing requirement
-                            let existing_parent = existing_version.required_by.last();
-                            self.find_conflict_parent(&current_req, existing_parent)
-                        };
-
-                    if let Some(parent_req) = conflict_parent {
-                        self.resolve_conflict(&parent_req)?;
-                    } else {
-                        return Err(FailedRequirement {
-                            desc: current_req.name.clone(),
-                        });
-                    }
-                }
-            } else {
-                // We haven't yet selected a version for this package, so lets do it.
-                let versions = self.repo.get_versions(&current_req.name);
-                let compatible_versions_spec: Vec<_> = versions
-                    .iter()
-                    .filter(|v| current_req.matches(&v.version))
-                    .cloned()
-                    .collect();
-
-                let mut compatible_versions = self
-                    .strategy
-                    .filter_versions(compatible_versions_spec.clone());
-
-                compatible_versions = self.strategy.sort_versions(compatible_versions);
-
-                if compatible_versions.is_empty() {
-                    // No compatible versions for %s %s.
-                    self.errors
-                        .insert(current_req.name.clone(), (None, current_req.spec.clone()));
-                    if current_req.required_by.is_empty() {
-                        // Can't match a top level package. Try upgrading to a newer version.
-                        return Err(FailedRequirement {
-                            desc: current_req.name.clone(),
-                        });
-                    } else {
-                        let parent = self.find_conflict_parent(&current_req, None);
-                        if let Some(parent_req) = parent {
-                            self.resolve_conflict(&parent_req)?;
-                        } else {
-                            return Err(FailedRequirement {
-                                desc: current_req.name.clone(),
-                            });
-                        }
-                    }
-                } else {
-                    // Found %s versions for %s that match spec.
-                    let mut state = State {
-                        requirements: self.requirements.clone(),
-                        selected: self.selected.clone(),
-                        current_requirement: current_req.clone(),
-                        possibilities: compatible_versions,
-                        depth: self.depth,
-                        name: current_req.name.clone(),
-                    };
-                    let selected_version = state.possibilities.pop().unwrap();
-                    self.select_package(&selected_version, &current_req);
-                    self.states.push(state);
-                }
-            }
-        }
+    /// Take the versions of a lockfile into account.
+    pub fn locked(mut self, versions: HashMap<String, Version>, locking: Locking) -> Self {
+        self.locked = versions;
+        self.locking = Some(locking);
+        self
+    }
+
+    /// Give up once the search goes over `limits`.
+    pub fn limits(mut self, limits: ResolveLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Resolve for `target` instead of the current platform.
+    pub fn target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Only pick releases that work with this SuperCollider version.
+    pub fn supercollider(mut self, version: Option<Version>) -> Self {
+        self.supercollider = version;
+        self
+    }
+
+    /// Resolve for `environment`, i.e. both [`Self::target`] and [`Self::supercollider`].
+    pub fn environment(self, environment: &Environment) -> Self {
+        self.target(environment.target.clone())
+            .supercollider(environment.supercollider.clone())
+    }
 
-        Ok(&self.selected)
-    }
-
-    fn select_package(
-        &mut self,
-        package_version: &PackageVersion,
-        current_req: &PackageRequirement,
-    ) {
-        // Selecting version %s
-        let mut pv = package_version.clone();
-        pv.required_by = current_req.required_by.clone();
-        pv.required_by.push(current_req.clone());
-        self.selected.insert(pv.name.clone(), pv.clone());
-
-        // Adding dependencies for package %s: %s
-        let mut dependencies = self.repo.get_dependencies(&pv);
-        for dep in dependencies.iter_mut() {
-            dep.required_by = current_req.required_by.clone();
-            dep.required_by.push(current_req.clone());
+    pub fn resolve(&mut self) -> Result<&HashMap<String, PackageVersion>, ResolveError> {
+        let requirements = self
+            .requirements
+            .iter()
+            .map(|req| (req.name.clone(), req.spec.clone()))
+            .collect::<Vec<_>>();
+        let repo = self
+            .repo
+            .for_target(&self.target)
+            .with_supercollider(self.supercollider.as_ref());
+        let mut solver = Solver::new(&repo, &self.strategy).with_limits(self.limits);
+        if let Some(locking) = self.locking {
+            solver = solver.with_locked(&self.locked, locking);
+        }
+        match solver.solve(&requirements) {
+            Ok(versions) => {
+                self.selected = self.with_requirement_chains(&repo, versions.into_iter().collect());
+                Ok(&self.selected)
+            }
+            Err(Failure::Conflict(incompatibility)) => {
+                Err(conflict_report(&solver, incompatibility).into())
+            }
+            Err(Failure::Aborted(reason)) => Err(ResolveAborted {
+                reason,
+                steps: solver.steps(),
+                contended: solver.contended(5),
+            }
+            .into()),
         }
-        self.requirements.extend(dependencies);
     }
 
-    fn find_conflict_parent(
+    /// Attach to each selected package the chain of requirements that first reaches it from
+    /// the top-level requirements, ending with its own, and the features asked of it.
+    fn with_requirement_chains(
         &self,
-        current_req: &PackageRequirement,
-        existing_req: Option<&PackageRequirement>,
-    ) -> Option<PackageRequirement> {
-        let mut current = Some(current_req.clone());
-        let mut existing = existing_req.cloned();
-
-        while current.is_some() || existing.is_some() {
-            // If there were other possible version choices in either the current or the already selected
-            // package, return one of those
-            if let Some(ref cur) = current {
-                if let Some(state) = self.find_state(Some(cur)) {
-                    if !state.possibilities.is_empty() {
-                        return Some(cur.clone());
-                    }
-                }
+        repo: &Repository,
+        versions: HashMap<String, Version>,
+    ) -> HashMap<String, PackageVersion> {
+        let mut selected: HashMap<String, PackageVersion> = HashMap::new();
+        let mut features: HashMap<String, Vec<String>> = HashMap::new();
+        let mut queue = self.requirements.iter().cloned().collect::<VecDeque<_>>();
+        while let Some(requirement) = queue.pop_front() {
+            let (package, feature) = split_features(&requirement.name);
+            if package == supercollider::PACKAGE {
+                continue;
             }
-            if let Some(ref ex) = existing {
-                if let Some(state) = self.find_state(Some(ex)) {
-                    if !state.possibilities.is_empty() {
-                        return Some(ex.clone());
-                    }
+            if let Some(feature) = feature.first() {
+                let enabled = features.entry(package.to_string()).or_default();
+                if enabled.iter().any(|f| f == feature) {
+                    continue;
                 }
+                enabled.push(feature.to_string());
+            } else if selected.contains_key(&requirement.name) {
+                continue;
             }
+            let Some(version) = versions.get(&requirement.name) else {
+                continue;
+            };
 
-            // If not, we need to walk one level back up the requirements chain and check again
-            current = current.and_then(|c| c.required_by.last().cloned());
-            existing = existing.and_then(|e| e.required_by.last().cloned());
-        }
+            let mut required_by = requirement.required_by.clone();
+            required_by.push(requirement.clone());
+            let package_version = PackageVersion {
+                name: requirement.name.clone(),
+                version: version.clone(),
+                required_by: required_by.clone(),
+                features: Vec::new(),
+            };
 
-        // Requirement for %s and existing requirement %s conflict, and no possible resolution can be found.
-        None
-    }
-
-    fn find_state(&self, requirement: Option<&PackageRequirement>) -> Option<&State> {
-        if let Some(req) = requirement {
-            for state in &self.states {
-                if state.name == req.name {
-                    return Some(state);
+            let mut dependencies = repo.get_dependencies(&package_version);
+            dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+            for mut dependency in dependencies {
+                if dependency.name == package {
+                    // A feature's dependency on its own package, which is already selected.
+                    continue;
                 }
+                dependency.required_by = required_by.clone();
+                queue.push_back(dependency);
+            }
+            // A package provided by another one stands for that one's release, selected next.
+            let provided = repo.provider(package, version).is_some();
+            if package == requirement.name && !provided {
+                selected.insert(requirement.name, package_version);
             }
         }
-        None
-    }
-
-    fn reset_state(&mut self, state: &State) {
-        if state.requirements.is_empty() && state.selected.is_empty() {
-            // Null state!
-            panic!("Null state!");
-        }
-
-        // roll states stack back to this state
-        while let Some(popped) = self.states.pop() {
-            if popped.name == state.name {
-                break;
+        for (package, mut enabled) in features {
+            if let Some(package_version) = selected.get_mut(&package) {
+                enabled.sort();
+                package_version.features = enabled;
             }
         }
+        selected
+    }
+}
+
+/// Summarise why the solver failed, around the package whose requirements clash the most.
+fn conflict_report(solver: &Solver, incompatibility: IncompatibilityId) -> ConflictReport {
+    let facts = solver.facts(incompatibility);
+    let target = |fact: &Fact| match fact {
+        Fact::Requested { name, .. }
+        | Fact::NoVersions { name, .. }
+        | Fact::Locked { name, .. } => name.clone(),
+        Fact::Dependency { dependency, .. } => dependency.clone(),
+        Fact::Conflict { other, .. } => other.clone(),
+    };
+    let is_requirer = |name: &str| {
+        facts
+            .iter()
+            .any(|fact| {
+                matches!(fact, Fact::Dependency { name: n, .. } | Fact::Conflict { name: n, .. } if n == name)
+            })
+    };
 
-        self.requirements = state.requirements.clone();
-        self.selected = state.selected.clone();
-        self.depth = state.depth;
+    // Prefer the most constrained package, then one that does not depend on the others.
+    let mut counts = std::collections::BTreeMap::new();
+    for fact in facts.iter() {
+        *counts.entry(target(fact)).or_insert(0) += 1;
     }
+    let package = counts
+        .iter()
+        .max_by(|(a_name, a), (b_name, b)| {
+            a.cmp(b)
+                .then_with(|| is_requirer(b_name).cmp(&is_requirer(a_name)))
+                .then_with(|| b_name.cmp(a_name))
+        })
+        .map(|(name, _)| name.clone())
+        .unwrap_or_default();
 
-    fn resolve_conflict(
-        &mut self,
-        requirement: &PackageRequirement,
-    ) -> Result<(), FailedRequirement> {
-        let maybe_state = self.find_state(Some(requirement)).cloned();
+    let mut requirements = facts
+        .iter()
+        .filter(|fact| target(fact) == package)
+        .filter_map(|fact| match fact {
+            Fact::Requested { name, spec } => Some(ConflictingRequirement {
+                name: name.clone(),
+                spec: spec.clone(),
+                chain: Vec::new(),
+                conflict: false,
+            }),
+            Fact::Dependency {
+                name,
+                versions,
+                dependency,
+                spec,
+            } => Some(ConflictingRequirement {
+                name: dependency.clone(),
+                spec: spec.clone(),
+                chain: requirement_chain(&facts, name, versions),
+                conflict: false,
+            }),
+            Fact::Conflict {
+                name,
+                versions,
+                other,
+                spec,
+            } => Some(ConflictingRequirement {
+                name: other.clone(),
+                spec: spec.clone(),
+                chain: requirement_chain(&facts, name, versions),
+                conflict: true,
+            }),
+            Fact::NoVersions { .. } | Fact::Locked { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    requirements.sort_by(|a, b| {
+        let requirer = |r: &ConflictingRequirement| r.chain.last().map(|p| p.name.clone());
+        requirer(a).cmp(&requirer(b))
+    });
 
-        // Rewinding to conflict state %s to find a new dependency resolution.
-        if let Some(mut state) = maybe_state {
-            if let Some(selected_version) = state.possibilities.pop() {
-                let current_requirement = state.current_requirement.clone();
-                self.reset_state(&state);
-                self.select_package(&selected_version, &current_requirement);
+    ConflictReport {
+        tried: solver.tried(&package),
+        available: solver.available(&package),
+        explanation: solver.explain(incompatibility),
+        package,
+        requirements,
+    }
+}
 
-                // If state has other possible versions remaining, keep it around
-                if !state.possibilities.is_empty() {
-                    self.states.push(state);
-                }
-                Ok(())
-            } else {
-                Err(FailedRequirement {
-                    desc: requirement.name.clone(),
-                })
+/// Walk the dependency facts back from `name` towards a top-level requirement.
+fn requirement_chain(facts: &[Fact], name: &str, versions: &str) -> Vec<Requirer> {
+    let mut chain = vec![Requirer {
+        name: name.to_string(),
+        versions: versions.to_string(),
+    }];
+    let mut current = name.to_string();
+    loop {
+        let parent = facts.iter().find_map(|fact| match fact {
+            Fact::Dependency {
+                name,
+                versions,
+                dependency,
+                ..
+            } if dependency == &current => Some((name, versions)),
+            _ => None,
+        });
+        let requested = facts
+            .iter()
+            .any(|fact| matches!(fact, Fact::Requested { name, .. } if name == &current));
+        match parent {
+            Some((name, versions)) if !requested && chain.iter().all(|r| &r.name != name) => {
+                chain.insert(
+                    0,
+                    Requirer {
+                        name: name.clone(),
+                        versions: versions.clone(),
+                    },
+                );
+                current = name.clone();
             }
-        } else {
-            Err(FailedRequirement {
-                desc: requirement.name.clone(),
-            })
+            _ => return chain,
         }
     }
 }
@@ -0,0 +1,244 @@
+use miette::Diagnostic;
+use reqwest::Client;
+use std::io::{Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum DownloadError {
+    #[error("Failed to download {url}: {source}")]
+    Network { url: String, source: reqwest::Error },
+
+    #[error("Failed to read {path}: {source}")]
+    IO {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to unpack archive from {url}: {reason}")]
+    #[diagnostic(help("Releases must be zip or gzipped tar archives."))]
+    Archive { url: String, reason: String },
+
+    #[error("Failed to clone {url}: {reason}")]
+    #[diagnostic(help("Overrides from git need git on the PATH and access to the repository."))]
+    Git { url: String, reason: String },
+}
+
+/// A single file or directory read from an archive, before it is written to disk.
+struct Entry {
+    path: PathBuf,
+    data: Option<Vec<u8>>,
+}
+
+/// Fetch the contents at `url`. `file://` urls are read from the local filesystem.
+pub async fn fetch(url: &str) -> Result<Vec<u8>, DownloadError> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return std::fs::read(path).map_err(|source| DownloadError::IO {
+            path: PathBuf::from(path),
+            source,
+        });
+    }
+
+    let network_error = |source| DownloadError::Network {
+        url: url.to_string(),
+        source,
+    };
+    let response = Client::new()
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(network_error)?;
+    let bytes = response.bytes().await.map_err(network_error)?;
+    Ok(bytes.to_vec())
+}
+
+/// Unpack a zip or tar.gz archive into `destination`, replacing anything already there.
+/// If every entry lives under a single top-level directory (as with GitHub archives), that
+/// directory is stripped.
+pub fn unpack(url: &str, data: &[u8], destination: &Path) -> Result<(), DownloadError> {
+    let archive_error = |reason: String| DownloadError::Archive {
+        url: url.to_string(),
+        reason,
+    };
+
+    let entries = if data.starts_with(b"PK\x03\x04") {
+        read_zip(data).map_err(archive_error)?
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        read_tar_gz(data).map_err(archive_error)?
+    } else {
+        return Err(archive_error("unrecognized archive format".to_string()));
+    };
+
+    let prefix = common_root(&entries);
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| DownloadError::IO { path, source }
+    };
+
+    if destination.exists() {
+        std::fs::remove_dir_all(destination).map_err(io_error(destination))?;
+    }
+    std::fs::create_dir_all(destination).map_err(io_error(destination))?;
+
+    for entry in entries {
+        let relative = match &prefix {
+            Some(prefix) => entry.path.strip_prefix(prefix).unwrap_or(&entry.path),
+            None => &entry.path,
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        let target = destination.join(relative);
+        match entry.data {
+            None => std::fs::create_dir_all(&target).map_err(io_error(&target))?,
+            Some(data) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+                }
+                std::fs::write(&target, data).map_err(io_error(&target))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_zip(data: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+        let path = file
+            .enclosed_name()
+            .ok_or_else(|| format!("unsafe path in archive: {}", file.name()))?;
+        if file.is_dir() {
+            entries.push(Entry { path, data: None });
+        } else {
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| e.to_string())?;
+            entries.push(Entry {
+                path,
+                data: Some(contents),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn read_tar_gz(data: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(data));
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path().map_err(|e| e.to_string())?.to_path_buf();
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("unsafe path in archive: {}", path.display()));
+        }
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            entries.push(Entry { path, data: None });
+        } else if entry_type.is_file() {
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .map_err(|e| e.to_string())?;
+            entries.push(Entry {
+                path,
+                data: Some(contents),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn common_root(entries: &[Entry]) -> Option<PathBuf> {
+    let mut root: Option<&std::ffi::OsStr> = None;
+    for entry in entries {
+        let mut components = entry
+            .path
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir));
+        let first = match components.next() {
+            Some(Component::Normal(first)) => first,
+            _ => return None,
+        };
+        // A file at the top level means there is no wrapping directory.
+        if entry.data.is_some() && components.next().is_none() {
+            return None;
+        }
+        match root {
+            Some(existing) if existing != first => return None,
+            _ => root = Some(first),
+        }
+    }
+    root.map(PathBuf::from)
+}
+
+/// Copy the directory `source` to `destination`, replacing anything already there. A `.git`
+/// directory is left out.
+pub fn copy_dir(source: &Path, destination: &Path) -> Result<(), DownloadError> {
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| DownloadError::IO { path, source }
+    };
+
+    if destination.exists() {
+        std::fs::remove_dir_all(destination).map_err(io_error(destination))?;
+    }
+    std::fs::create_dir_all(destination).map_err(io_error(destination))?;
+    for entry in std::fs::read_dir(source).map_err(io_error(source))? {
+        let entry = entry.map_err(io_error(source))?;
+        let path = entry.path();
+        let target = destination.join(entry.file_name());
+        if path.is_dir() {
+            if entry.file_name() != ".git" {
+                copy_dir(&path, &target)?;
+            }
+        } else {
+            std::fs::copy(&path, &target).map_err(io_error(&path))?;
+        }
+    }
+    Ok(())
+}
+
+/// Clone the git repository at `url` into `destination`, replacing anything already there,
+/// and check out `rev` if given.
+pub fn clone_git(url: &str, rev: Option<&str>, destination: &Path) -> Result<(), DownloadError> {
+    let git_error = |reason: String| DownloadError::Git {
+        url: url.to_string(),
+        reason,
+    };
+    let run = |command: &mut std::process::Command| {
+        let output = command.output().map_err(|e| git_error(e.to_string()))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(git_error(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    };
+
+    if destination.exists() {
+        std::fs::remove_dir_all(destination).map_err(|source| DownloadError::IO {
+            path: destination.to_path_buf(),
+            source,
+        })?;
+    }
+    run(std::process::Command::new("git")
+        .args(["clone", "--quiet", url])
+        .arg(destination))?;
+    if let Some(rev) = rev {
+        run(std::process::Command::new("git")
+            .arg("-C")
+            .arg(destination)
+            .args(["checkout", "--quiet", rev]))?;
+    }
+    Ok(())
+}
@@ -79,7 +79,13 @@ where
 
     async fn load_from_remote(&mut self) -> Result<(), EndpointError> {
         let client = Client::new();
-        let response = client.get(&self.query.url).send().await?;
+        let method = reqwest::Method::from_bytes(self.query.method.as_bytes())
+            .unwrap_or(reqwest::Method::GET);
+        let mut request = client.request(method, &self.query.url);
+        for (key, value) in self.query.headers.iter() {
+            request = request.header(key, value);
+        }
+        let response = request.send().await?;
 
         if response.status().is_success() {
             let cache_time = response.headers().get("Last-Modified").and_then(|v| {
@@ -92,8 +98,13 @@ where
             let text = response.text().await?;
             let value = serde_json::from_str::<T>(&text)?;
 
+            if let Some(parent) = std::path::Path::new(&self.cache_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
             let mut file = std::fs::File::options()
                 .write(true)
+                .create(true)
+                .truncate(true)
                 .open(&self.cache_path)?;
             file.write_all(text.as_bytes())?;
             file.set_modified(cache_time.unwrap_or(SystemTime::now()))?;
@@ -0,0 +1,121 @@
+use miette::Diagnostic;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the file, inside the repository path, that records installed packages.
+pub const INSTALLED_FILE: &str = "installed.json";
+
+/// Directory a package is unpacked into, inside the repository path. Names that would point
+/// anywhere else are refused, as the directory gets replaced on install.
+pub fn package_path(repository_path: &Path, package_name: &str) -> Result<PathBuf, InstalledError> {
+    check_directory_name(package_name).map_err(|reason| InstalledError::UnsafeName {
+        name: package_name.to_string(),
+        reason,
+    })?;
+    Ok(repository_path.join(package_name))
+}
+
+/// Whether `name` can name a file or directory of its own inside another directory: not empty,
+/// `.` or `..`, and without path separators or a drive prefix.
+pub fn check_directory_name(name: &str) -> Result<(), String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        _ if name.contains(['/', '\\']) => Err("it contains a path separator".to_string()),
+        (Some(std::path::Component::Normal(part)), None) if part == name => Ok(()),
+        _ => Err("it is not a plain directory name".to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: Version,
+    pub url: String,
+    /// The requirement given when this package was installed explicitly; `None` for dependencies.
+    #[serde(default)]
+    pub requirement: Option<String>,
+    /// Features asked for when this package was installed explicitly.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Names of the packages that pulled this one in, from the top-level requirement downwards.
+    #[serde(default)]
+    pub required_by: Vec<String>,
+    /// Dependencies of the installed release, by name and version requirement.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    /// Checksum of the archive the package was unpacked from.
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Packages the installed release provides or replaces, by name and the version it counts as.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub provides: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InstalledPackages {
+    pub packages: BTreeMap<String, InstalledPackage>,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum InstalledError {
+    #[error("Failed to read or write installed packages at {path}: {source}")]
+    #[diagnostic(help("The file may be corrupt; remove it to reset the installed package list."))]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to access installed packages at {path}: {source}")]
+    IO {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Refusing to use {name:?} as a package directory: {reason}")]
+    UnsafeName { name: String, reason: String },
+}
+
+impl InstalledPackages {
+    /// Load the installed package list from a repository path. A missing file means nothing is installed.
+    pub fn load(repository_path: &Path) -> Result<Self, InstalledError> {
+        let path = repository_path.join(INSTALLED_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = std::fs::read_to_string(&path).map_err(|source| InstalledError::IO {
+            path: path.clone(),
+            source,
+        })?;
+        serde_json::from_str(&data).map_err(|source| InstalledError::Json { path, source })
+    }
+
+    pub fn save(&self, repository_path: &Path) -> Result<(), InstalledError> {
+        let path = repository_path.join(INSTALLED_FILE);
+        let data = serde_json::to_string_pretty(self).map_err(|source| InstalledError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        std::fs::create_dir_all(repository_path)
+            .and_then(|_| std::fs::write(&path, data))
+            .map_err(|source| InstalledError::IO { path, source })
+    }
+
+    pub fn get(&self, package_name: &str) -> Option<&InstalledPackage> {
+        self.packages.get(package_name)
+    }
+
+    /// The installed packages that meet a dependency on `package_name`: the package itself, and
+    /// any that provide or replace it.
+    pub fn providers<'a>(
+        &'a self,
+        package_name: &'a str,
+    ) -> impl Iterator<Item = &'a InstalledPackage> + 'a {
+        self.packages
+            .values()
+            .filter(move |p| p.name == package_name || p.provides.contains_key(package_name))
+    }
+}
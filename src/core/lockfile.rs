@@ -0,0 +1,217 @@
+use crate::core::dependencies::PackageVersion;
+use crate::core::settings::PackageOverride;
+use miette::Diagnostic;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the lockfile, in the project directory.
+pub const LOCK_FILE: &str = "baryon.lock";
+
+/// Format version written to new lockfiles.
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    pub url: String,
+    /// The configured repository the release was taken from.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// `sha256:<hex>` of the downloaded archive, if it has been downloaded.
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// Names of the selected packages that depend on this one.
+    #[serde(default)]
+    pub required_by: Vec<String>,
+    /// Features enabled for this package, whose optional dependencies were selected too.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// The override the package was taken from, if it did not come from the index as is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<PackageOverride>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCK_VERSION,
+            packages: BTreeMap::new(),
+        }
+    }
+}
+
+/// One difference between a lockfile and a resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum LockChange {
+    Added {
+        name: String,
+        version: String,
+    },
+    Removed {
+        name: String,
+        version: String,
+    },
+    Changed {
+        name: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl fmt::Display for LockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockChange::Added { name, version } => write!(f, "+ {} {}", name, version),
+            LockChange::Removed { name, version } => write!(f, "- {} {}", name, version),
+            LockChange::Changed { name, from, to } => write!(f, "~ {} {} -> {}", name, from, to),
+        }
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum LockError {
+    #[error("Failed to read or write lockfile {path}: {source}")]
+    #[diagnostic(help("The file may be corrupt; remove it to resolve from scratch."))]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to access lockfile {path}: {source}")]
+    IO {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("{path} is out of date:\n{changes}")]
+    #[diagnostic(help("Run the command without --locked to update the lockfile."))]
+    Stale { path: PathBuf, changes: String },
+
+    #[error(
+        "Checksum mismatch for {name}: the lockfile has {expected}, but the download is {actual}"
+    )]
+    #[diagnostic(help(
+        "The release changed after it was locked. Check its source before updating the lockfile."
+    ))]
+    Checksum {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Lockfile {
+    /// Load a lockfile. A missing file means there is no lock yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, LockError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = std::fs::read_to_string(path).map_err(|source| LockError::IO {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|source| LockError::Json {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LockError> {
+        let data = serde_json::to_string_pretty(self).map_err(|source| LockError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|source| LockError::IO {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        }
+        std::fs::write(path, data + "\n").map_err(|source| LockError::IO {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn get(&self, package_name: &str) -> Option<&LockedPackage> {
+        self.packages.get(package_name)
+    }
+
+    /// The locked version of every package.
+    pub fn versions(&self) -> HashMap<String, Version> {
+        self.packages
+            .values()
+            .map(|p| (p.name.clone(), p.version.clone()))
+            .collect()
+    }
+
+    /// What would change in this lockfile if it recorded `selected` instead, sorted by name.
+    pub fn diff(&self, selected: &[PackageVersion]) -> Vec<LockChange> {
+        let selected = selected
+            .iter()
+            .map(|pv| (pv.name.as_str(), &pv.version))
+            .collect::<BTreeMap<_, _>>();
+        let mut changes = Vec::new();
+        for (name, version) in selected.iter() {
+            match self.get(name) {
+                None => changes.push(LockChange::Added {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                }),
+                Some(locked) if &locked.version != *version => changes.push(LockChange::Changed {
+                    name: name.to_string(),
+                    from: locked.version.to_string(),
+                    to: version.to_string(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for locked in self.packages.values() {
+            if !selected.contains_key(locked.name.as_str()) {
+                changes.push(LockChange::Removed {
+                    name: locked.name.clone(),
+                    version: locked.version.to_string(),
+                });
+            }
+        }
+        changes.sort_by(|a, b| change_name(a).cmp(change_name(b)));
+        changes
+    }
+}
+
+fn change_name(change: &LockChange) -> &str {
+    match change {
+        LockChange::Added { name, .. }
+        | LockChange::Removed { name, .. }
+        | LockChange::Changed { name, .. } => name,
+    }
+}
+
+/// The lockfile that goes with the project manifest at `manifest`, next to it. There is none
+/// without a manifest, so global installs leave no lockfile behind.
+pub fn project_lockfile(manifest: &Path) -> Option<PathBuf> {
+    manifest
+        .exists()
+        .then(|| manifest.with_file_name(LOCK_FILE))
+}
+
+/// Checksum of a downloaded archive, as recorded in lockfiles.
+pub fn checksum(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
@@ -0,0 +1,145 @@
+use crate::core::settings::{PackageOverride, RepositoryAuth, RepositorySource, Settings};
+use crate::specs::manifest::{Dependencies, Manifest};
+use miette::Diagnostic;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the project manifest, in the project directory.
+pub const MANIFEST_FILE: &str = "baryon.yaml";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ManifestError {
+    #[error("Failed to parse manifest {path}: {source}")]
+    #[diagnostic(help("See src/specs/manifest.json for the expected format."))]
+    Parse {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+
+    #[error("Failed to access manifest {path}: {source}")]
+    IO {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("The project manifest defines repository {name}, which is already configured")]
+    #[diagnostic(help(
+        "Give the manifest repository another name, or remove the configured one if the project should replace it."
+    ))]
+    RepositoryTaken { name: String },
+}
+
+/// Load a project manifest. A missing file means the directory is not a project.
+pub fn load(path: &Path) -> Result<Option<Manifest>, ManifestError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let data = std::fs::read_to_string(path).map_err(|source| ManifestError::IO {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    serde_yaml::from_str(&data)
+        .map(Some)
+        .map_err(|source| ManifestError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Everything the manifest asks for, by name and version requirement. Dev-dependencies are
+/// only included with `dev`, and never override a regular dependency.
+pub fn requirements(manifest: &Manifest, dev: bool) -> BTreeMap<String, String> {
+    let entries = |dependencies: &Option<Dependencies>| {
+        dependencies
+            .iter()
+            .flat_map(|d| d.iter())
+            .map(|(name, requirement)| (name.to_string(), requirement.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let mut requirements = BTreeMap::new();
+    if dev {
+        requirements.extend(entries(&manifest.dev_dependencies));
+    }
+    requirements.extend(entries(&manifest.dependencies));
+    requirements
+}
+
+/// Add the manifest's repositories to the configured ones. A manifest may not take over a
+/// configured repository's name, and its tokens are only expanded from the environment if
+/// `expand_manifest_tokens` is set, as the manifest picks where they are sent.
+pub fn apply_repositories(
+    manifest: &Manifest,
+    settings: &mut Settings,
+) -> Result<(), ManifestError> {
+    if manifest.repositories.is_empty() {
+        return Ok(());
+    }
+
+    let mut sources = settings.repository_sources();
+    for repository in manifest.repositories.iter() {
+        if sources.iter().any(|s| s.name == repository.name) {
+            return Err(ManifestError::RepositoryTaken {
+                name: repository.name.clone(),
+            });
+        }
+        let token = |token: &String| match settings.expand_manifest_tokens {
+            true => shellexpand::env(token)
+                .map(|token| token.to_string())
+                .unwrap_or_else(|_| token.clone()),
+            false => {
+                if token.contains('$') {
+                    tracing::warn!(
+                        "not expanding the token of repository {} from the project manifest; set expand_manifest_tokens to allow it",
+                        repository.name
+                    );
+                }
+                token.clone()
+            }
+        };
+        sources.push(RepositorySource {
+            name: repository.name.clone(),
+            url: repository.url.clone(),
+            priority: repository.priority,
+            auth: repository.auth.as_ref().map(|auth| RepositoryAuth {
+                token: token(&auth.token),
+                header: auth.header.clone(),
+            }),
+        });
+    }
+    settings.repositories = sources;
+    Ok(())
+}
+
+/// Add the manifest's overrides to the configured ones, replacing any for the same package.
+/// Relative override paths are taken from `project_dir`, the directory of the manifest.
+pub fn apply_overrides(manifest: &Manifest, project_dir: &Path, settings: &mut Settings) {
+    for (name, entry) in manifest.overrides.iter() {
+        settings.overrides.insert(
+            name.to_string(),
+            PackageOverride {
+                version: entry.version.clone(),
+                path: entry
+                    .path
+                    .as_ref()
+                    .map(|path| project_dir.join(path).to_string_lossy().to_string()),
+                git: entry.git.clone(),
+                rev: entry.rev.clone(),
+            },
+        );
+    }
+}
+
+/// Load the project manifest at `path`, if there is one, and apply its repositories and
+/// overrides to `settings`.
+pub fn load_into(path: &Path, settings: &mut Settings) -> Result<Option<Manifest>, ManifestError> {
+    let project = load(path)?;
+    if let Some(project) = &project {
+        let project_dir = path.parent().unwrap_or(Path::new(""));
+        apply_repositories(project, settings)?;
+        apply_overrides(project, project_dir, settings);
+    }
+    Ok(project)
+}
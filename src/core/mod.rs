@@ -1,4 +1,13 @@
+pub mod config;
 pub mod dependencies;
+pub mod download;
 pub mod http;
+pub mod installed;
+pub mod lockfile;
+pub mod manifest;
 pub mod repository;
 pub mod settings;
+pub mod solver;
+pub mod supercollider;
+pub mod target;
+pub mod version;
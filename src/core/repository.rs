@@ -1,36 +1,238 @@
-use std::collections::HashMap;
+use miette::Diagnostic;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use thiserror::Error;
 
-use crate::core::http::{Query, RemoteEndpoint};
-use crate::specs::{self, Package, Repository as RepositoryDesc};
+use crate::core::dependencies::InvalidRelease;
+use crate::core::http::{CacheSettings, EndpointError, Query, RemoteEndpoint};
+use crate::core::installed::check_directory_name;
+use crate::specs::{self, Package, Release, Repository as RepositoryDesc};
+use serde::de::Error as _;
+use serde_json::{Map, Value};
 
-use super::settings::Settings;
+use super::settings::{RepositorySource, Settings, DEFAULT_REPOSITORY};
+use super::version::parse_version;
 
 pub struct HTTPRepository {
-    repo_endpoint: RemoteEndpoint<RepositoryDesc>,
+    repo_endpoint: RemoteEndpoint<Value>,
     desc: RepositoryDesc,
+    invalid: Vec<InvalidRelease>,
 }
 
 pub trait Repository {
     fn get_packages(&self) -> Vec<&Package>;
     fn get_package(&self, package_name: &str) -> Option<&Package>;
+
+    /// Name of the configured repository a package comes from, if known.
+    fn source_of(&self, _package_name: &str) -> Option<&str> {
+        None
+    }
+
+    /// Releases left out of the packages because they do not match the index schema.
+    fn invalid_releases(&self) -> Vec<&InvalidRelease> {
+        Vec::new()
+    }
 }
 
 impl HTTPRepository {
     pub fn new(settings: &Settings) -> Self {
-        let repo_endpoint = RemoteEndpoint::new(
+        Self::with_query(
             &settings.cache_settings,
+            "repository.json",
             Query {
                 url: settings.repository_url.clone(),
-                method: "POST".to_string(),
+                method: "GET".to_string(),
                 headers: vec![],
             },
-        );
+        )
+    }
+
+    /// A repository for one of several configured sources, cached under its own name in a
+    /// directory of its own, apart from the single-index `repository.json`.
+    pub fn from_source(source: &RepositorySource, cache_settings: &CacheSettings) -> Self {
+        Self::with_query(
+            cache_settings,
+            &format!("repositories/{}.json", source.name),
+            Query {
+                url: source.url.clone(),
+                method: "GET".to_string(),
+                headers: source.auth.iter().map(|auth| auth.header()).collect(),
+            },
+        )
+    }
+
+    fn with_query(cache_settings: &CacheSettings, cache_file: &str, query: Query) -> Self {
+        let cache_settings = CacheSettings {
+            cache_path: Path::new(&cache_settings.cache_path)
+                .join(cache_file)
+                .to_string_lossy()
+                .to_string(),
+            cache_timeout: cache_settings.cache_timeout,
+        };
+        let repo_endpoint = RemoteEndpoint::new(&cache_settings, query);
 
         Self {
             repo_endpoint,
             desc: specs::Repository(HashMap::new()),
+            invalid: Vec::new(),
         }
     }
+
+    /// Create a repository and populate it from the remote index (or the local cache, if fresh).
+    pub async fn load(settings: &Settings) -> Result<Self, EndpointError> {
+        let mut repo = Self::new(settings);
+        repo.refresh().await?;
+        Ok(repo)
+    }
+
+    /// Re-read the index through the endpoint, replacing the current package list.
+    pub async fn refresh(&mut self) -> Result<(), EndpointError> {
+        let schema = self.repo_endpoint.data().await?;
+        let packages = schema
+            .get("repository")
+            .cloned()
+            .ok_or_else(|| serde_json::Error::missing_field("repository"))?;
+        (self.desc, self.invalid) = read_index(packages)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum RepositoryError {
+    #[error("Failed to load repository {name}: {source}")]
+    Source {
+        name: String,
+        #[source]
+        #[diagnostic_source]
+        source: EndpointError,
+    },
+
+    #[error("Invalid repository name {name:?}: {reason}")]
+    #[diagnostic(help("Repository names are used as cache file names, so they cannot be paths."))]
+    InvalidName { name: String, reason: String },
+}
+
+/// A repository together with the name and priority it was configured with.
+pub struct NamedRepository {
+    pub name: String,
+    pub priority: i64,
+    pub repository: Box<dyn Repository>,
+}
+
+/// Several repositories merged into one. When more than one has a package, the one with the
+/// highest priority wins, then the one configured first. Pinned packages only ever come from
+/// the repository they are pinned to.
+pub struct CompositeRepository {
+    packages: HashMap<String, Package>,
+    sources: HashMap<String, String>,
+    invalid: Vec<InvalidRelease>,
+}
+
+impl CompositeRepository {
+    pub fn new(mut repositories: Vec<NamedRepository>, pins: &BTreeMap<String, String>) -> Self {
+        // Stable sort keeps configuration order for equal priorities.
+        repositories.sort_by_key(|repository| std::cmp::Reverse(repository.priority));
+
+        for (package, pinned) in pins.iter() {
+            if !repositories.iter().any(|named| &named.name == pinned) {
+                tracing::warn!(
+                    "{} is pinned to repository {}, which is not configured, so it is not available",
+                    package,
+                    pinned
+                );
+            }
+        }
+
+        let mut packages = HashMap::new();
+        let mut sources = HashMap::new();
+        for named in repositories.iter() {
+            for package in named.repository.get_packages() {
+                let allowed = pins
+                    .get(&package.name)
+                    .is_none_or(|pinned| pinned == &named.name);
+                if allowed && !packages.contains_key(&package.name) {
+                    packages.insert(package.name.clone(), package.clone());
+                    sources.insert(package.name.clone(), named.name.clone());
+                }
+            }
+        }
+        let invalid = repositories
+            .iter()
+            .flat_map(|named| {
+                named
+                    .repository
+                    .invalid_releases()
+                    .into_iter()
+                    .filter(|release| sources.get(&release.package) == Some(&named.name))
+                    .cloned()
+            })
+            .collect();
+
+        Self {
+            packages,
+            sources,
+            invalid,
+        }
+    }
+
+    /// Load every configured repository and merge them.
+    pub async fn load(settings: &Settings) -> Result<Self, RepositoryError> {
+        let mut repositories = Vec::new();
+        if settings.repositories.is_empty() {
+            let repository =
+                HTTPRepository::load(settings)
+                    .await
+                    .map_err(|source| RepositoryError::Source {
+                        name: DEFAULT_REPOSITORY.to_string(),
+                        source,
+                    })?;
+            repositories.push(NamedRepository {
+                name: DEFAULT_REPOSITORY.to_string(),
+                priority: 0,
+                repository: Box::new(repository),
+            });
+        }
+
+        for source in settings.repositories.iter() {
+            check_directory_name(&source.name).map_err(|reason| RepositoryError::InvalidName {
+                name: source.name.clone(),
+                reason,
+            })?;
+            let mut repository = HTTPRepository::from_source(source, &settings.cache_settings);
+            repository
+                .refresh()
+                .await
+                .map_err(|e| RepositoryError::Source {
+                    name: source.name.clone(),
+                    source: e,
+                })?;
+            repositories.push(NamedRepository {
+                name: source.name.clone(),
+                priority: source.priority,
+                repository: Box::new(repository),
+            });
+        }
+
+        Ok(Self::new(repositories, &settings.pins))
+    }
+}
+
+impl Repository for CompositeRepository {
+    fn get_package(&self, package_name: &str) -> Option<&Package> {
+        self.packages.get(package_name)
+    }
+
+    fn get_packages(&self) -> Vec<&Package> {
+        self.packages.values().collect()
+    }
+
+    fn source_of(&self, package_name: &str) -> Option<&str> {
+        self.sources.get(package_name).map(|s| s.as_str())
+    }
+
+    fn invalid_releases(&self) -> Vec<&InvalidRelease> {
+        self.invalid.iter().collect()
+    }
 }
 
 impl Repository for HTTPRepository {
@@ -38,6 +240,108 @@ impl Repository for HTTPRepository {
         self.desc.get(package_name)
     }
     fn get_packages(&self) -> Vec<&Package> {
-        self.desc.iter().map(|(_, package)| package).collect()
+        self.desc.values().collect()
+    }
+    fn invalid_releases(&self) -> Vec<&InvalidRelease> {
+        self.invalid.iter().collect()
+    }
+}
+
+/// Read the packages of an index, keyed by name. A release that does not match the schema is
+/// left out of its package and returned as invalid, so one bad release does not take the whole
+/// index down; whether that is an error is up to the dependency index.
+pub fn read_index(
+    packages: Value,
+) -> Result<(RepositoryDesc, Vec<InvalidRelease>), serde_json::Error> {
+    let Value::Object(packages) = packages else {
+        return Err(serde_json::Error::custom(
+            "expected the packages of the index, keyed by name",
+        ));
+    };
+
+    let mut desc = HashMap::new();
+    let mut invalid = Vec::new();
+    for (key, mut package) in packages {
+        let releases = match package.get_mut("releases") {
+            Some(Value::Array(releases)) => std::mem::take(releases),
+            _ => Vec::new(),
+        };
+        let mut package: Package = serde_json::from_value(package)?;
+        for release in releases {
+            match serde_json::from_value::<Release>(release.clone()) {
+                Ok(parsed) => package.releases.push(parsed),
+                Err(e) => invalid.push(invalid_release(&package.name, &release, e)),
+            }
+        }
+        desc.insert(key, package);
+    }
+    Ok((specs::Repository(desc), invalid))
+}
+
+/// Describe why `release` of `package` does not match the schema, naming the field at fault.
+fn invalid_release(package: &str, release: &Value, error: serde_json::Error) -> InvalidRelease {
+    let show = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    // Each field is tried on its own, next to placeholders for the required ones, and the
+    // entries of a field that holds named entries one at a time.
+    let fits = |field: &str, value: &Value| {
+        let mut alone = Map::new();
+        alone.insert("version".to_string(), Value::from("0.0.0"));
+        alone.insert("url".to_string(), Value::from("https://example.com"));
+        alone.insert(field.to_string(), value.clone());
+        serde_json::from_value::<Release>(Value::Object(alone)).is_ok()
+    };
+    let fault = release.as_object().and_then(|fields| {
+        fields
+            .iter()
+            .find(|(field, value)| !fits(field, value))
+            .map(|(field, value)| {
+                let entry = value.as_object().and_then(|entries| {
+                    entries.iter().find(|(name, entry)| {
+                        let single = Map::from_iter([(name.to_string(), (*entry).clone())]);
+                        !fits(field, &Value::Object(single))
+                    })
+                });
+                match entry {
+                    Some((name, entry)) => (format!("{}.{}", field, name), show(entry)),
+                    None => (field.to_string(), show(value)),
+                }
+            })
+    });
+    let (field, value) = fault.unwrap_or_else(|| ("release".to_string(), show(release)));
+    InvalidRelease {
+        package: package.to_string(),
+        release: release.get("version").map(show).unwrap_or_default(),
+        field,
+        value,
+        reason: error.to_string(),
+        help: None,
+    }
+}
+
+/// The highest release of a package, preferring stable releases over prereleases.
+pub fn latest_version(package: &Package) -> Option<semver::Version> {
+    let versions = package
+        .releases
+        .iter()
+        .filter_map(|release| parse_version(&release.version).ok())
+        .collect::<Vec<_>>();
+
+    let stable = versions.iter().filter(|v| v.pre.is_empty()).max();
+    stable.or_else(|| versions.iter().max()).cloned()
+}
+
+/// The release of a package matching `version` exactly. `None` if several releases read as
+/// `version`, as the dependency index skips all of them.
+pub fn find_release<'a>(package: &'a Package, version: &semver::Version) -> Option<&'a Release> {
+    let mut matching = package
+        .releases
+        .iter()
+        .filter(|release| parse_version(&release.version).is_ok_and(|v| &v == version));
+    match (matching.next(), matching.next()) {
+        (Some(release), None) => Some(release),
+        _ => None,
     }
 }
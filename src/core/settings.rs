@@ -1,7 +1,124 @@
+use crate::core::dependencies::ResolveLimits;
 use crate::core::http::CacheSettings;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name given to the repository built from `repository_url` when no repositories are configured.
+pub const DEFAULT_REPOSITORY: &str = "default";
 
 pub struct Settings {
     pub global_repository_path: String,
     pub repository_url: String,
+    pub repositories: Vec<RepositorySource>,
+    /// Packages that must come from a specific repository, by package name.
+    pub pins: BTreeMap<String, String>,
+    /// Packages to take from somewhere other than the index, by package name.
+    pub overrides: BTreeMap<String, PackageOverride>,
     pub cache_settings: CacheSettings,
+    pub resolve_limits: ResolveLimits,
+    /// Fail on invalid releases in the package index instead of skipping them.
+    pub strict_repository: bool,
+    /// Expand `${VAR}` in the tokens of project manifest repositories.
+    pub expand_manifest_tokens: bool,
+    /// SuperCollider version to install for, if configured rather than detected.
+    pub supercollider_version: Option<Version>,
+}
+
+/// A named package index. When several indexes have a package, the highest priority wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepositorySource {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<RepositoryAuth>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepositoryAuth {
+    /// Sent as `Authorization: Bearer <token>`, unless `header` names another header.
+    pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+}
+
+/// Where to take a package from instead of its index release. A forced `version` is used no
+/// matter what other packages require; a `path` or `git` source replaces the release archive,
+/// while dependencies still come from the index release that is resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// A local directory, e.g. a checkout being worked on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// A git repository to clone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Branch, tag or commit to check out from `git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+impl PackageOverride {
+    /// Whether the package is installed from `path` or `git` rather than downloaded.
+    pub fn has_source(&self) -> bool {
+        self.path.is_some() || self.git.is_some()
+    }
+}
+
+impl std::fmt::Display for PackageOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(version) = &self.version {
+            parts.push(format!("version {}", version));
+        }
+        if let Some(path) = &self.path {
+            parts.push(format!("path {}", path));
+        }
+        if let Some(git) = &self.git {
+            match &self.rev {
+                Some(rev) => parts.push(format!("git {}#{}", git, rev)),
+                None => parts.push(format!("git {}", git)),
+            }
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+impl RepositorySource {
+    /// The repository built from `repository_url` when no repositories are configured.
+    pub fn default_for(url: &str) -> Self {
+        Self {
+            name: DEFAULT_REPOSITORY.to_string(),
+            url: url.to_string(),
+            priority: 0,
+            auth: None,
+        }
+    }
+}
+
+impl RepositoryAuth {
+    pub fn header(&self) -> (String, String) {
+        match &self.header {
+            Some(header) => (header.clone(), self.token.clone()),
+            None => (
+                "Authorization".to_string(),
+                format!("Bearer {}", self.token),
+            ),
+        }
+    }
+}
+
+impl Settings {
+    /// The configured repositories, or a single default one built from `repository_url`.
+    pub fn repository_sources(&self) -> Vec<RepositorySource> {
+        if self.repositories.is_empty() {
+            vec![RepositorySource::default_for(&self.repository_url)]
+        } else {
+            self.repositories.clone()
+        }
+    }
 }
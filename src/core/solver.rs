@@ -0,0 +1,1121 @@
+//! Conflict-driven version solving, following the PubGrub algorithm described in
+//! <https://github.com/dart-lang/pub/blob/master/doc/solver.md>.
+//!
+//! Every package has a finite list of known versions, so a set of versions is kept as a bit set
+//! over the indices of that list. Index 0 stands for "not selected", which lets a single set
+//! express both positive terms ("foo is selected at one of these versions") and negative terms
+//! ("foo is not selected at any of these versions").
+
+use super::dependencies::{AbortReason, Locking, Repository, ResolveLimits, Strategy};
+use super::supercollider;
+use super::version::VersionSpec;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use tracing::{debug, info, trace};
+
+pub(crate) type PackageId = usize;
+pub(crate) type IncompatibilityId = usize;
+
+/// The index that stands for "not selected" in every version set.
+const NONE: usize = 0;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct VersionSet {
+    bits: Vec<u64>,
+}
+
+impl VersionSet {
+    /// Every index below `size`.
+    fn full(size: usize) -> Self {
+        let mut set = Self::default();
+        for index in 0..size {
+            set.insert(index);
+        }
+        set
+    }
+
+    fn single(index: usize) -> Self {
+        let mut set = Self::default();
+        set.insert(index);
+        set
+    }
+
+    fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if self.bits.len() <= word {
+            self.bits.resize(word + 1, 0);
+        }
+        self.bits[word] |= 1 << (index % 64);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.bits
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        Self {
+            bits: self
+                .bits
+                .iter()
+                .zip(other.bits.iter())
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+        .trimmed()
+    }
+
+    /// Every index below `size` that is not in this set.
+    fn complement(&self, size: usize) -> Self {
+        let full = Self::full(size);
+        Self {
+            bits: full
+                .bits
+                .iter()
+                .enumerate()
+                .map(|(i, word)| word & !self.bits.get(i).copied().unwrap_or(0))
+                .collect(),
+        }
+        .trimmed()
+    }
+
+    fn is_subset(&self, other: &Self) -> bool {
+        self.bits
+            .iter()
+            .enumerate()
+            .all(|(i, word)| word & !other.bits.get(i).copied().unwrap_or(0) == 0)
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.bits
+            .iter()
+            .zip(other.bits.iter())
+            .all(|(a, b)| a & b == 0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+
+    fn trimmed(mut self) -> Self {
+        while self.bits.last() == Some(&0) {
+            self.bits.pop();
+        }
+        self
+    }
+}
+
+/// A statement about one package: "its assignment is in `set`".
+#[derive(Debug, Clone)]
+pub(crate) struct Term {
+    package: PackageId,
+    set: VersionSet,
+}
+
+impl Term {
+    fn is_positive(&self) -> bool {
+        !self.set.contains(NONE)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Cause {
+    /// A top-level requirement.
+    Requested {
+        package: PackageId,
+        spec: VersionSpec,
+    },
+    /// `versions` of `package` depend on `dependency` matching `spec`.
+    Dependency {
+        package: PackageId,
+        versions: VersionSet,
+        dependency: PackageId,
+        spec: VersionSpec,
+    },
+    /// `versions` of `package` cannot be installed alongside `other` matching `spec`.
+    Conflict {
+        package: PackageId,
+        versions: VersionSet,
+        other: PackageId,
+        spec: VersionSpec,
+    },
+    /// None of `versions` of `package` may be picked under the current strategy.
+    NoVersions {
+        package: PackageId,
+        versions: VersionSet,
+    },
+    /// `package` is locked to `version`.
+    Locked {
+        package: PackageId,
+        version: Version,
+    },
+    /// Learned from two other incompatibilities during conflict resolution.
+    Derived(IncompatibilityId, IncompatibilityId),
+}
+
+/// A set of terms that must not all be true at once.
+#[derive(Debug, Clone)]
+pub(crate) struct Incompatibility {
+    terms: Vec<Term>,
+    cause: Cause,
+}
+
+/// Why [`Solver::solve`] failed.
+#[derive(Debug, Clone)]
+pub(crate) enum Failure {
+    /// No solution exists, as proven by this incompatibility.
+    Conflict(IncompatibilityId),
+    /// The search went over one of its limits before finding an answer.
+    Aborted(AbortReason),
+}
+
+/// An external fact that a failed resolution was derived from.
+#[derive(Debug, Clone)]
+pub(crate) enum Fact {
+    Requested {
+        name: String,
+        spec: VersionSpec,
+    },
+    Dependency {
+        name: String,
+        versions: String,
+        dependency: String,
+        spec: VersionSpec,
+    },
+    Conflict {
+        name: String,
+        versions: String,
+        other: String,
+        spec: VersionSpec,
+    },
+    NoVersions {
+        name: String,
+        versions: String,
+    },
+    Locked {
+        name: String,
+        version: Version,
+    },
+}
+
+#[derive(Debug)]
+struct PackageInfo {
+    name: String,
+    /// Ascending. Version `i` has index `i + 1` in version sets.
+    versions: Vec<Version>,
+}
+
+#[derive(Debug)]
+struct Assignment {
+    package: PackageId,
+    set: VersionSet,
+    /// The intersection of this and every earlier assignment to the same package.
+    accumulated: VersionSet,
+    level: usize,
+    /// `None` for decisions.
+    cause: Option<IncompatibilityId>,
+}
+
+enum Relation {
+    Satisfied,
+    Contradicted,
+    AlmostSatisfied(usize),
+    Inconclusive,
+}
+
+pub(crate) struct Solver<'a> {
+    repo: &'a Repository,
+    strategy: &'a Strategy,
+    locked: Option<(&'a HashMap<String, Version>, Locking)>,
+    packages: Vec<PackageInfo>,
+    ids: HashMap<String, PackageId>,
+    incompatibilities: Vec<Incompatibility>,
+    by_package: Vec<Vec<IncompatibilityId>>,
+    assignments: Vec<Assignment>,
+    package_assignments: Vec<Vec<usize>>,
+    decisions: HashMap<PackageId, usize>,
+    level: usize,
+    dependencies_added: HashSet<(PackageId, PackageId, VersionSpec)>,
+    conflicts_added: HashSet<(PackageId, PackageId, VersionSpec)>,
+    tried: HashMap<PackageId, Vec<usize>>,
+    /// Packages named by the requirements passed to `solve`.
+    direct: HashSet<PackageId>,
+    limits: ResolveLimits,
+    started: Instant,
+    /// Backjumps made so far.
+    steps: usize,
+    /// How often each package took part in a conflict.
+    conflicts: HashMap<PackageId, usize>,
+}
+
+impl<'a> Solver<'a> {
+    pub(crate) fn new(repo: &'a Repository, strategy: &'a Strategy) -> Self {
+        Self {
+            repo,
+            strategy,
+            locked: None,
+            packages: Vec::new(),
+            ids: HashMap::new(),
+            incompatibilities: Vec::new(),
+            by_package: Vec::new(),
+            assignments: Vec::new(),
+            package_assignments: Vec::new(),
+            decisions: HashMap::new(),
+            level: 0,
+            dependencies_added: HashSet::new(),
+            conflicts_added: HashSet::new(),
+            tried: HashMap::new(),
+            direct: HashSet::new(),
+            limits: ResolveLimits::default(),
+            started: Instant::now(),
+            steps: 0,
+            conflicts: HashMap::new(),
+        }
+    }
+
+    /// Prefer or require the given versions over what the strategy would pick.
+    pub(crate) fn with_locked(
+        mut self,
+        versions: &'a HashMap<String, Version>,
+        locking: Locking,
+    ) -> Self {
+        self.locked = Some((versions, locking));
+        self
+    }
+
+    /// Give up once the search goes over `limits`.
+    pub(crate) fn with_limits(mut self, limits: ResolveLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Find a version for every package reachable from `requirements`.
+    pub(crate) fn solve(
+        &mut self,
+        requirements: &[(String, VersionSpec)],
+    ) -> Result<Vec<(String, Version)>, Failure> {
+        info!("resolving {} requirements", requirements.len());
+        self.started = Instant::now();
+        let mut requested = Vec::new();
+        for (name, spec) in requirements {
+            let package = self.package_id(name);
+            let allowed = self.matching(package, spec);
+            let id = self.add_incompatibility(
+                vec![Term {
+                    package,
+                    set: allowed.complement(self.size(package)),
+                }],
+                Cause::Requested {
+                    package,
+                    spec: spec.clone(),
+                },
+            );
+            if self.incompatibilities[id].terms.is_empty() {
+                return Err(Failure::Conflict(id));
+            }
+            self.register(id);
+            requested.push(package);
+            self.direct.insert(package);
+        }
+        if let Some((versions, Locking::Require)) = self.locked {
+            let mut versions = versions.iter().collect::<Vec<_>>();
+            versions.sort();
+            for (name, version) in versions {
+                let package = self.package_id(name);
+                let mut others = self.versions(package);
+                let index = self.index_of(package, version);
+                if index != NONE {
+                    others = others
+                        .intersection(&VersionSet::single(index).complement(self.size(package)));
+                }
+                let id = self.add_incompatibility(
+                    vec![Term {
+                        package,
+                        set: others,
+                    }],
+                    Cause::Locked {
+                        package,
+                        version: version.clone(),
+                    },
+                );
+                self.register(id);
+            }
+        }
+        for package in requested {
+            self.propagate(package).map_err(Failure::Conflict)?;
+        }
+
+        while let Some(package) = self.next_package() {
+            self.check_limits()?;
+            let candidates = self
+                .current(package)
+                .iter()
+                .filter(|&index| index != NONE)
+                .map(|index| self.packages[package].versions[index - 1].clone())
+                .collect::<Vec<_>>();
+
+            // Yanked versions are only picked when locked.
+            let name = &self.packages[package].name;
+            let unyanked = candidates
+                .iter()
+                .filter(|version| !self.repo.is_yanked(name, version))
+                .cloned()
+                .collect::<Vec<_>>();
+            let locked = self
+                .locked
+                .and_then(|(versions, _)| versions.get(name))
+                .filter(|version| candidates.contains(version));
+            let Some(version) = locked.or_else(|| {
+                let direct = self.direct.contains(&package);
+                self.strategy.choose(name, direct, &unyanked)
+            }) else {
+                let versions = self.current(package).intersection(&self.versions(package));
+                let id = self.add_incompatibility(
+                    vec![Term {
+                        package,
+                        set: versions.clone(),
+                    }],
+                    Cause::NoVersions { package, versions },
+                );
+                self.register(id);
+                self.propagate(package).map_err(Failure::Conflict)?;
+                continue;
+            };
+            let index = self.index_of(package, version);
+
+            self.add_dependencies(package, index);
+            self.decide(package, index);
+            self.propagate(package).map_err(Failure::Conflict)?;
+        }
+
+        let mut selected = self
+            .decisions
+            .iter()
+            .map(|(&package, &index)| {
+                let info = &self.packages[package];
+                (info.name.clone(), info.versions[index - 1].clone())
+            })
+            .collect::<Vec<_>>();
+        selected.sort();
+        info!(
+            "resolved {} packages in {} steps",
+            selected.len(),
+            self.steps
+        );
+        Ok(selected)
+    }
+
+    /// Backjumps made so far.
+    pub(crate) fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Up to `count` packages that took part in the most conflicts, with how many, most first.
+    pub(crate) fn contended(&self, count: usize) -> Vec<(String, usize)> {
+        let mut contended = self
+            .conflicts
+            .iter()
+            .map(|(&package, &conflicts)| (self.packages[package].name.clone(), conflicts))
+            .collect::<Vec<_>>();
+        contended.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+        contended.truncate(count);
+        contended
+    }
+
+    /// Every version of `name` known to the repository, ascending.
+    pub(crate) fn available(&self, name: &str) -> Vec<Version> {
+        self.ids
+            .get(name)
+            .map(|&package| self.packages[package].versions.clone())
+            .unwrap_or_default()
+    }
+
+    /// Versions of `name` that were selected at some point, ascending.
+    pub(crate) fn tried(&self, name: &str) -> Vec<Version> {
+        let Some(&package) = self.ids.get(name) else {
+            return Vec::new();
+        };
+        let mut indices = self.tried.get(&package).cloned().unwrap_or_default();
+        indices.sort();
+        indices.dedup();
+        indices
+            .into_iter()
+            .map(|index| self.packages[package].versions[index - 1].clone())
+            .collect()
+    }
+
+    /// The external facts behind an incompatibility, in the order they were used.
+    pub(crate) fn facts(&self, id: IncompatibilityId) -> Vec<Fact> {
+        let mut facts = Vec::new();
+        let mut seen = HashSet::new();
+        self.collect_facts(id, &mut facts, &mut seen);
+        facts
+    }
+
+    /// A step-by-step derivation of an incompatibility from external facts.
+    pub(crate) fn explain(&self, id: IncompatibilityId) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut seen = HashSet::new();
+        self.explain_into(id, &mut lines, &mut seen);
+        lines
+    }
+
+    //////////////////////////////////////////////////////////////////////////
+    // Packages and version sets
+
+    fn package_id(&mut self, name: &str) -> PackageId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let mut versions = self
+            .repo
+            .get_versions(name)
+            .into_iter()
+            .map(|pv| pv.version)
+            .collect::<Vec<_>>();
+        versions.sort();
+
+        let id = self.packages.len();
+        self.packages.push(PackageInfo {
+            name: name.to_string(),
+            versions,
+        });
+        self.ids.insert(name.to_string(), id);
+        self.by_package.push(Vec::new());
+        self.package_assignments.push(Vec::new());
+        id
+    }
+
+    /// Number of indices in the package's version sets, including `NONE`.
+    fn size(&self, package: PackageId) -> usize {
+        self.packages[package].versions.len() + 1
+    }
+
+    /// Every version of the package, without `NONE`.
+    fn versions(&self, package: PackageId) -> VersionSet {
+        VersionSet::single(NONE).complement(self.size(package))
+    }
+
+    fn matching(&self, package: PackageId, spec: &VersionSpec) -> VersionSet {
+        let mut set = VersionSet::default();
+        for (i, version) in self.packages[package].versions.iter().enumerate() {
+            if spec.matches(version) {
+                set.insert(i + 1);
+            }
+        }
+        set
+    }
+
+    fn index_of(&self, package: PackageId, version: &Version) -> usize {
+        self.packages[package]
+            .versions
+            .binary_search(version)
+            .map(|i| i + 1)
+            .unwrap_or(NONE)
+    }
+
+    //////////////////////////////////////////////////////////////////////////
+    // Incompatibilities
+
+    /// Store an incompatibility, merging terms on the same package and dropping terms that are
+    /// always true.
+    fn add_incompatibility(&mut self, terms: Vec<Term>, cause: Cause) -> IncompatibilityId {
+        let mut merged: Vec<Term> = Vec::new();
+        for term in terms {
+            match merged.iter_mut().find(|t| t.package == term.package) {
+                Some(existing) => existing.set = existing.set.intersection(&term.set),
+                None => merged.push(term),
+            }
+        }
+        merged.retain(|term| !VersionSet::full(self.size(term.package)).is_subset(&term.set));
+
+        self.incompatibilities.push(Incompatibility {
+            terms: merged,
+            cause,
+        });
+        self.incompatibilities.len() - 1
+    }
+
+    /// Make an incompatibility take part in unit propagation.
+    fn register(&mut self, id: IncompatibilityId) {
+        for term in self.incompatibilities[id].terms.iter() {
+            self.by_package[term.package].push(id);
+        }
+    }
+
+    /// Add the dependencies of `package` at `index`, each covering every version of `package`
+    /// with the same requirement.
+    fn add_dependencies(&mut self, package: PackageId, index: usize) {
+        let repo = self.repo;
+        let name = self.packages[package].name.clone();
+        let versions = self.packages[package].versions.clone();
+        let dependencies_of = |version: &Version| {
+            repo.data
+                .get(&name)
+                .and_then(|releases| releases.get(version))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let mut dependencies = dependencies_of(&versions[index - 1]);
+        dependencies.sort_by(|a, b| a.name.cmp(&b.name));
+        let all_dependencies = versions.iter().map(dependencies_of).collect::<Vec<_>>();
+
+        for requirement in dependencies {
+            let dependency = self.package_id(&requirement.name);
+            let key = (package, dependency, requirement.spec.clone());
+            if !self.dependencies_added.insert(key) {
+                continue;
+            }
+
+            let mut depending = VersionSet::default();
+            for (i, deps) in all_dependencies.iter().enumerate() {
+                if deps
+                    .iter()
+                    .any(|d| d.name == requirement.name && d.spec == requirement.spec)
+                {
+                    depending.insert(i + 1);
+                }
+            }
+            let allowed = self.matching(dependency, &requirement.spec);
+            let id = self.add_incompatibility(
+                vec![
+                    Term {
+                        package,
+                        set: depending.clone(),
+                    },
+                    Term {
+                        package: dependency,
+                        set: allowed.complement(self.size(dependency)),
+                    },
+                ],
+                Cause::Dependency {
+                    package,
+                    versions: depending,
+                    dependency,
+                    spec: requirement.spec.clone(),
+                },
+            );
+            self.register(id);
+        }
+
+        self.add_conflicts(package, index);
+    }
+
+    /// Rule out the packages that `package` at `index` cannot be installed alongside. Only
+    /// their own releases conflict, not other packages that provide them.
+    fn add_conflicts(&mut self, package: PackageId, index: usize) {
+        let repo = self.repo;
+        let name = self.packages[package].name.clone();
+        let versions = self.packages[package].versions.clone();
+        let conflicts_of = |version: &Version| {
+            repo.conflicts
+                .get(&name)
+                .and_then(|releases| releases.get(version))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let all_conflicts = versions.iter().map(conflicts_of).collect::<Vec<_>>();
+        for requirement in conflicts_of(&versions[index - 1]) {
+            let other = self.package_id(&requirement.name);
+            let key = (package, other, requirement.spec.clone());
+            if !self.conflicts_added.insert(key) {
+                continue;
+            }
+
+            let mut conflicting = VersionSet::default();
+            for (i, conflicts) in all_conflicts.iter().enumerate() {
+                if conflicts
+                    .iter()
+                    .any(|c| c.name == requirement.name && c.spec == requirement.spec)
+                {
+                    conflicting.insert(i + 1);
+                }
+            }
+            let mut ruled_out = VersionSet::default();
+            for i in self.matching(other, &requirement.spec).iter() {
+                let version = &self.packages[other].versions[i - 1];
+                if repo.provider(&requirement.name, version).is_none() {
+                    ruled_out.insert(i);
+                }
+            }
+            if ruled_out == VersionSet::default() {
+                continue;
+            }
+            let id = self.add_incompatibility(
+                vec![
+                    Term {
+                        package,
+                        set: conflicting.clone(),
+                    },
+                    Term {
+                        package: other,
+                        set: ruled_out,
+                    },
+                ],
+                Cause::Conflict {
+                    package,
+                    versions: conflicting,
+                    other,
+                    spec: requirement.spec.clone(),
+                },
+            );
+            self.register(id);
+        }
+    }
+
+    //////////////////////////////////////////////////////////////////////////
+    // Partial solution
+
+    fn current(&self, package: PackageId) -> VersionSet {
+        self.package_assignments[package]
+            .last()
+            .map(|&i| self.assignments[i].accumulated.clone())
+            .unwrap_or_else(|| VersionSet::full(self.size(package)))
+    }
+
+    fn assign(&mut self, package: PackageId, set: VersionSet, cause: Option<IncompatibilityId>) {
+        let accumulated = self.current(package).intersection(&set);
+        self.package_assignments[package].push(self.assignments.len());
+        self.assignments.push(Assignment {
+            package,
+            set,
+            accumulated,
+            level: self.level,
+            cause,
+        });
+    }
+
+    fn check_limits(&self) -> Result<(), Failure> {
+        if self.limits.max_steps.is_some_and(|max| self.steps >= max) {
+            return Err(Failure::Aborted(AbortReason::Steps(self.steps)));
+        }
+        let elapsed = self.started.elapsed();
+        if self
+            .limits
+            .timeout
+            .is_some_and(|timeout| elapsed >= timeout)
+        {
+            return Err(Failure::Aborted(AbortReason::Timeout(elapsed)));
+        }
+        Ok(())
+    }
+
+    fn decide(&mut self, package: PackageId, index: usize) {
+        debug!(
+            "decide {} {} at level {}",
+            self.packages[package].name,
+            self.packages[package].versions[index - 1],
+            self.level + 1
+        );
+        self.level += 1;
+        self.assign(package, VersionSet::single(index), None);
+        self.decisions.insert(package, index);
+        self.tried.entry(package).or_default().push(index);
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        debug!("backtrack from level {} to level {}", self.level, level);
+        self.steps += 1;
+        while self.assignments.last().is_some_and(|a| a.level > level) {
+            if let Some(assignment) = self.assignments.pop() {
+                self.package_assignments[assignment.package].pop();
+                if assignment.cause.is_none() {
+                    self.decisions.remove(&assignment.package);
+                }
+            }
+        }
+        self.level = level;
+    }
+
+    /// The required package with the fewest remaining versions that has not been decided yet.
+    fn next_package(&self) -> Option<PackageId> {
+        (0..self.packages.len())
+            .filter(|package| !self.decisions.contains_key(package))
+            .map(|package| (package, self.current(package)))
+            .filter(|(_, set)| !set.contains(NONE))
+            .min_by(|(a, a_set), (b, b_set)| {
+                a_set
+                    .iter()
+                    .count()
+                    .cmp(&b_set.iter().count())
+                    .then_with(|| self.packages[*a].name.cmp(&self.packages[*b].name))
+            })
+            .map(|(package, _)| package)
+    }
+
+    //////////////////////////////////////////////////////////////////////////
+    // Propagation and conflict resolution
+
+    fn relation(&self, id: IncompatibilityId) -> Relation {
+        let mut unsatisfied = None;
+        for (i, term) in self.incompatibilities[id].terms.iter().enumerate() {
+            let current = self.current(term.package);
+            if current.is_subset(&term.set) {
+                continue;
+            }
+            if current.is_disjoint(&term.set) {
+                return Relation::Contradicted;
+            }
+            if unsatisfied.is_some() {
+                return Relation::Inconclusive;
+            }
+            unsatisfied = Some(i);
+        }
+        match unsatisfied {
+            Some(i) => Relation::AlmostSatisfied(i),
+            None => Relation::Satisfied,
+        }
+    }
+
+    fn propagate(&mut self, package: PackageId) -> Result<(), IncompatibilityId> {
+        let mut changed = vec![package];
+        while let Some(package) = changed.pop() {
+            for id in self.by_package[package].clone().into_iter().rev() {
+                match self.relation(id) {
+                    Relation::Satisfied => {
+                        let learned = self.resolve_conflict(id)?;
+                        changed.clear();
+                        if let Relation::AlmostSatisfied(term) = self.relation(learned) {
+                            changed.push(self.derive(learned, term));
+                        }
+                        break;
+                    }
+                    Relation::AlmostSatisfied(term) => changed.push(self.derive(id, term)),
+                    Relation::Contradicted | Relation::Inconclusive => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Assign the negation of the one unsatisfied term of an incompatibility.
+    fn derive(&mut self, id: IncompatibilityId, term: usize) -> PackageId {
+        let term = &self.incompatibilities[id].terms[term];
+        let package = term.package;
+        let set = term.set.complement(self.size(package));
+        trace!(
+            "derive {} {} from {}",
+            self.packages[package].name,
+            self.describe_versions(package, &set),
+            self.describe(id)
+        );
+        self.assign(package, set, Some(id));
+        package
+    }
+
+    /// The first assignment after which `term` is satisfied, if it ever is. With `extra`,
+    /// assignments are considered together with that additional set.
+    fn satisfier(&self, term: &Term, before: usize, extra: Option<&VersionSet>) -> Option<usize> {
+        self.package_assignments[term.package]
+            .iter()
+            .copied()
+            .filter(|&i| i < before)
+            .find(|&i| {
+                let accumulated = &self.assignments[i].accumulated;
+                match extra {
+                    Some(extra) => accumulated.intersection(extra).is_subset(&term.set),
+                    None => accumulated.is_subset(&term.set),
+                }
+            })
+    }
+
+    /// Learn from a satisfied incompatibility and backjump to where it becomes useful again.
+    fn resolve_conflict(
+        &mut self,
+        mut id: IncompatibilityId,
+    ) -> Result<IncompatibilityId, IncompatibilityId> {
+        let mut learned = false;
+        loop {
+            debug!("conflict: {}", self.describe(id));
+            let terms = self.incompatibilities[id].terms.clone();
+            for term in terms.iter() {
+                *self.conflicts.entry(term.package).or_default() += 1;
+            }
+            if terms.is_empty() {
+                return Err(id);
+            }
+
+            let satisfiers = terms
+                .iter()
+                .map(|term| self.satisfier(term, self.assignments.len(), None))
+                .collect::<Vec<_>>();
+            let Some((term_index, satisfier)) = satisfiers
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.map(|s| (i, s)))
+                .max_by_key(|(_, s)| *s)
+            else {
+                return Err(id);
+            };
+            let term = &terms[term_index];
+            let satisfier_set = self.assignments[satisfier].set.clone();
+
+            let mut previous = satisfiers
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != term_index)
+                .filter_map(|(_, s)| *s)
+                .max();
+            if !VersionSet::full(self.size(term.package))
+                .intersection(&satisfier_set)
+                .is_subset(&term.set)
+            {
+                previous = previous.max(self.satisfier(term, satisfier, Some(&satisfier_set)));
+            }
+            let previous_level = previous.map(|i| self.assignments[i].level).unwrap_or(0);
+
+            let satisfier_level = self.assignments[satisfier].level;
+            let Some(cause) = self.assignments[satisfier]
+                .cause
+                .filter(|_| previous_level == satisfier_level)
+            else {
+                if learned {
+                    self.register(id);
+                }
+                self.backtrack(previous_level);
+                return Ok(id);
+            };
+
+            let package = term.package;
+            let mut prior = terms
+                .iter()
+                .chain(self.incompatibilities[cause].terms.iter())
+                .filter(|t| t.package != package)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !satisfier_set.is_subset(&term.set) {
+                let size = self.size(package);
+                let difference = satisfier_set.intersection(&term.set.complement(size));
+                prior.push(Term {
+                    package,
+                    set: difference.complement(size),
+                });
+            }
+
+            id = self.add_incompatibility(prior, Cause::Derived(id, cause));
+            learned = true;
+        }
+    }
+
+    //////////////////////////////////////////////////////////////////////////
+    // Explanations
+
+    fn collect_facts(
+        &self,
+        id: IncompatibilityId,
+        facts: &mut Vec<Fact>,
+        seen: &mut HashSet<IncompatibilityId>,
+    ) {
+        if !seen.insert(id) {
+            return;
+        }
+        let fact = match &self.incompatibilities[id].cause {
+            Cause::Derived(a, b) => {
+                self.collect_facts(*a, facts, seen);
+                self.collect_facts(*b, facts, seen);
+                return;
+            }
+            Cause::Requested { package, spec } => Fact::Requested {
+                name: self.packages[*package].name.clone(),
+                spec: spec.clone(),
+            },
+            Cause::Dependency {
+                package,
+                versions,
+                dependency,
+                spec,
+            } => Fact::Dependency {
+                name: self.packages[*package].name.clone(),
+                versions: self.describe_versions(*package, versions),
+                dependency: self.packages[*dependency].name.clone(),
+                spec: spec.clone(),
+            },
+            Cause::Conflict {
+                package,
+                versions,
+                other,
+                spec,
+            } => Fact::Conflict {
+                name: self.packages[*package].name.clone(),
+                versions: self.describe_versions(*package, versions),
+                other: self.packages[*other].name.clone(),
+                spec: spec.clone(),
+            },
+            Cause::NoVersions { package, versions } => Fact::NoVersions {
+                name: self.packages[*package].name.clone(),
+                versions: self.describe_versions(*package, versions),
+            },
+            Cause::Locked { package, version } => Fact::Locked {
+                name: self.packages[*package].name.clone(),
+                version: version.clone(),
+            },
+        };
+        facts.push(fact);
+    }
+
+    fn explain_into(
+        &self,
+        id: IncompatibilityId,
+        lines: &mut Vec<String>,
+        seen: &mut HashSet<IncompatibilityId>,
+    ) {
+        if let Cause::Derived(a, b) = self.incompatibilities[id].cause {
+            if !seen.insert(id) {
+                return;
+            }
+            self.explain_into(a, lines, seen);
+            self.explain_into(b, lines, seen);
+            lines.push(format!(
+                "Because {} and {}, {}.",
+                self.describe(a),
+                self.describe(b),
+                self.describe(id)
+            ));
+        }
+    }
+
+    fn describe(&self, id: IncompatibilityId) -> String {
+        let incompatibility = &self.incompatibilities[id];
+        let name = |package: &PackageId| supercollider::display_name(&self.packages[*package].name);
+        match &incompatibility.cause {
+            Cause::Requested { package, spec } => {
+                format!("{} {} is requested", name(package), spec)
+            }
+            Cause::Dependency {
+                package,
+                versions,
+                dependency,
+                spec,
+            } => format!(
+                "{} {} depends on {} {}",
+                name(package),
+                self.describe_versions(*package, versions),
+                name(dependency),
+                spec
+            ),
+            Cause::Conflict {
+                package,
+                versions,
+                other,
+                spec,
+            } => format!(
+                "{} {} conflicts with {} {}",
+                name(package),
+                self.describe_versions(*package, versions),
+                name(other),
+                spec
+            ),
+            Cause::NoVersions { package, versions } => {
+                let info = &self.packages[*package];
+                let yanked = versions
+                    .iter()
+                    .filter(|&index| index != NONE)
+                    .all(|index| self.repo.is_yanked(&info.name, &info.versions[index - 1]));
+                format!(
+                    "{} {} is {}",
+                    name(package),
+                    self.describe_versions(*package, versions),
+                    if yanked {
+                        "yanked"
+                    } else {
+                        "ruled out by the resolution strategy"
+                    }
+                )
+            }
+            Cause::Locked { package, version } => {
+                format!("{} is locked to {}", name(package), version)
+            }
+            Cause::Derived(..) => self.describe_terms(&incompatibility.terms),
+        }
+    }
+
+    fn describe_terms(&self, terms: &[Term]) -> String {
+        match terms {
+            [] => "version solving failed".to_string(),
+            [term] if term.is_positive() => format!("{} cannot be used", self.describe_term(term)),
+            [term] => format!("{} is required", self.describe_term(term)),
+            [a, b] if a.is_positive() != b.is_positive() => {
+                let (positive, negative) = if a.is_positive() { (a, b) } else { (b, a) };
+                format!(
+                    "{} requires {}",
+                    self.describe_term(positive),
+                    self.describe_term(negative)
+                )
+            }
+            _ => {
+                let described = terms
+                    .iter()
+                    .map(|term| {
+                        if term.is_positive() {
+                            self.describe_term(term)
+                        } else {
+                            format!("not {}", self.describe_term(term))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                match described.split_last() {
+                    Some((last, rest)) => {
+                        format!("{} and {} are incompatible", rest.join(", "), last)
+                    }
+                    None => String::new(),
+                }
+            }
+        }
+    }
+
+    /// The versions a term talks about: those it allows if positive, and those it rules out
+    /// if negative.
+    fn describe_term(&self, term: &Term) -> String {
+        let versions = if term.is_positive() {
+            term.set.clone()
+        } else {
+            term.set.complement(self.size(term.package))
+        };
+        format!(
+            "{} {}",
+            supercollider::display_name(&self.packages[term.package].name),
+            self.describe_versions(term.package, &versions)
+        )
+    }
+
+    /// A compact description of a set of versions, as runs over the package's known versions.
+    fn describe_versions(&self, package: PackageId, set: &VersionSet) -> String {
+        let versions = &self.packages[package].versions;
+        let indices = set.iter().filter(|&i| i != NONE).collect::<Vec<_>>();
+        // A package with a single version reads better with that version spelled out.
+        if indices.len() == versions.len() && versions.len() != 1 {
+            return "*".to_string();
+        }
+
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for index in indices {
+            match runs.last_mut() {
+                Some((_, end)) if *end + 1 == index => *end = index,
+                _ => runs.push((index, index)),
+            }
+        }
+        runs.iter()
+            .map(|&(start, end)| {
+                let (first, last) = (&versions[start - 1], &versions[end - 1]);
+                if start == end {
+                    first.to_string()
+                } else if start == 1 {
+                    format!("<={}", last)
+                } else if end == versions.len() {
+                    format!(">={}", first)
+                } else {
+                    format!(">={}, <={}", first, last)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" || ")
+    }
+}
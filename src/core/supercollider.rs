@@ -0,0 +1,41 @@
+//! The SuperCollider version packages are installed for.
+//!
+//! Releases can require a range of SuperCollider versions. While resolving, SuperCollider is a
+//! package named [`PACKAGE`] with a single version, the one configured or detected here, so
+//! releases that need another version are ruled out like any other incompatible dependency.
+//! Its name is not a valid package name, so it never clashes with a package in the index.
+
+use super::version::parse_version;
+use semver::Version;
+use std::process::Command;
+
+/// Name of the package standing for SuperCollider while resolving.
+pub const PACKAGE: &str = "@supercollider";
+
+/// How to name `package` in messages: SuperCollider for [`PACKAGE`], otherwise the package name.
+pub fn display_name(package: &str) -> &str {
+    if package == PACKAGE {
+        "SuperCollider"
+    } else {
+        package
+    }
+}
+
+/// The version of the `sclang` on the `PATH`, if there is one and it reports its version.
+pub fn detect() -> Option<Version> {
+    let output = Command::new("sclang").arg("-v").output().ok()?;
+    let version = version_in(&String::from_utf8_lossy(&output.stdout));
+    if version.is_none() {
+        tracing::debug!("could not read the SuperCollider version from sclang -v");
+    }
+    version
+}
+
+/// The first version in the output of `sclang -v`, e.g. `3.13.0` in
+/// `sclang 3.13.0 (Built from branch 'Version-3.13.0' [3188503])`.
+pub fn version_in(output: &str) -> Option<Version> {
+    output
+        .split_whitespace()
+        .filter(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .find_map(|word| parse_version(word).ok())
+}
@@ -0,0 +1,78 @@
+//! Platforms that dependencies can be conditional on.
+//!
+//! Operating systems and architectures are named as in [`std::env::consts::OS`] and
+//! [`std::env::consts::ARCH`], e.g. `linux`, `macos`, `windows` and `x86_64`, `aarch64`.
+
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The platform packages are resolved for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Target {
+    pub os: String,
+    pub arch: String,
+}
+
+impl Target {
+    /// The platform this program runs on.
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        }
+    }
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Invalid target {0:?}, expected <os>-<arch>")]
+#[diagnostic(
+    code(baryon::target::invalid),
+    help("Name the platform as in Rust's std::env::consts, e.g. linux-x86_64, macos-aarch64 or windows-x86_64.")
+)]
+pub struct TargetError(pub String);
+
+impl FromStr for Target {
+    type Err = TargetError;
+
+    /// Parse `<os>-<arch>`, e.g. `macos-aarch64`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once('-') {
+            Some((os, arch)) if !os.is_empty() && !arch.is_empty() => Ok(Self {
+                os: os.to_string(),
+                arch: arch.to_string(),
+            }),
+            _ => Err(TargetError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.os, self.arch)
+    }
+}
+
+/// The platforms a dependency applies to. An empty list allows any value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Condition {
+    pub os: Vec<String>,
+    pub arch: Vec<String>,
+}
+
+impl Condition {
+    pub fn matches(&self, target: &Target) -> bool {
+        let allows = |allowed: &[String], value: &str| {
+            allowed.is_empty() || allowed.iter().any(|a| a == value)
+        };
+        allows(&self.os, &target.os) && allows(&self.arch, &target.arch)
+    }
+}
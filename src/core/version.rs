@@ -0,0 +1,245 @@
+//! Version requirements as written in package indexes, manifests and on the command line.
+//!
+//! On top of what [`semver::VersionReq`] accepts, a requirement may be:
+//!
+//! - a union of alternatives separated by `||`, e.g. `^1.2 || ^2.0`, which matches a version if
+//!   any alternative does;
+//! - a hyphen range `A - B`, meaning `>=A, <=B`. A partial upper bound covers everything up to
+//!   the next release after it, so `1.2.0 - 1.4.x` is `>=1.2.0, <1.5.0` and `1 - 2` is
+//!   `>=1, <3.0.0`;
+//! - comparators separated by spaces instead of commas, e.g. `>=1.0.0 <2.0.0`.
+//!
+//! Release versions are read with [`parse_version`], which also accepts the looser forms found
+//! in older quarks; see [`VERSION_RULES`].
+
+use miette::Diagnostic;
+use semver::{BuildMetadata, Prerelease, Version, VersionReq};
+use std::fmt;
+use std::str::FromStr;
+
+/// A union of version requirements.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionSpec {
+    alternatives: Vec<VersionReq>,
+}
+
+impl VersionSpec {
+    pub fn parse(spec: &str) -> Result<Self, semver::Error> {
+        let alternatives = spec
+            .split("||")
+            .map(|alternative| VersionReq::parse(&desugar(alternative)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { alternatives })
+    }
+
+    /// Whether any alternative matches `version`.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.alternatives.iter().any(|req| req.matches(version))
+    }
+
+    pub fn alternatives(&self) -> &[VersionReq] {
+        &self.alternatives
+    }
+}
+
+impl From<VersionReq> for VersionSpec {
+    fn from(req: VersionReq) -> Self {
+        Self {
+            alternatives: vec![req],
+        }
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = semver::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alternatives = self
+            .alternatives
+            .iter()
+            .map(|req| req.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", alternatives.join(" || "))
+    }
+}
+
+const OPERATORS: &[char] = &['=', '>', '<', '~', '^'];
+
+/// Rewrite one alternative into the comma-separated syntax of [`VersionReq`].
+fn desugar(alternative: &str) -> String {
+    let tokens = alternative
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+
+    if let [lower, "-", upper] = tokens.as_slice() {
+        return match upper_bound(upper) {
+            Some(upper) => format!("{}, {}", lower_bound(lower), upper),
+            None => lower_bound(lower),
+        };
+    }
+
+    // Operators may be written apart from their version, as in `>= 1.0.0`.
+    let mut comparators = Vec::new();
+    let mut operator = String::new();
+    for token in tokens {
+        if token.chars().all(|c| OPERATORS.contains(&c)) {
+            operator.push_str(token);
+        } else {
+            comparators.push(format!("{}{}", operator, token));
+            operator.clear();
+        }
+    }
+    if !operator.is_empty() {
+        comparators.push(operator);
+    }
+    comparators.join(", ")
+}
+
+/// The numeric components of a partial version, up to the first wildcard.
+fn numeric_parts(version: &str) -> Vec<&str> {
+    version
+        .split('.')
+        .take_while(|part| !matches!(*part, "x" | "X" | "*"))
+        .collect()
+}
+
+fn lower_bound(version: &str) -> String {
+    let parts = numeric_parts(version);
+    if parts.is_empty() {
+        ">=0.0.0".to_string()
+    } else if parts.len() == 3 || version.contains('-') {
+        format!(">={}", version)
+    } else {
+        format!(">={}", parts.join("."))
+    }
+}
+
+/// `None` if the range has no upper bound, as in `1.0.0 - *`.
+fn upper_bound(version: &str) -> Option<String> {
+    let parts = numeric_parts(version);
+    let number = |i: usize| parts[i].parse::<u64>().ok();
+    match parts.len() {
+        0 => None,
+        1 => match number(0) {
+            Some(major) => Some(format!("<{}.0.0", major + 1)),
+            None => Some(format!("<={}", version)),
+        },
+        2 if !version.contains('-') => match (number(0), number(1)) {
+            (Some(major), Some(minor)) => Some(format!("<{}.{}.0", major, minor + 1)),
+            _ => Some(format!("<={}", version)),
+        },
+        _ => Some(format!("<={}", version)),
+    }
+}
+
+/// How [`parse_version`] maps versions that are not valid semver.
+pub const VERSION_RULES: &str = "\
+Versions that are not valid semver are read as follows:
+  - surrounding spaces and a leading `v` are ignored: v0.3 -> 0.3.0
+  - missing minor and patch numbers are 0, leading zeros are dropped: 1.2 -> 1.2.0, 01.2 -> 1.2.0
+  - text after the numbers is a prerelease, split where letters and digits meet: \
+1.0beta -> 1.0.0-beta, 2.1b2 -> 2.1.0-b.2
+  - build metadata after `+` is kept as it is
+A version needs one to three numbers, and a prerelease only letters and digits separated by \
+`.` or `-`.";
+
+/// A release version that could not be read, even leniently.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Cannot read version {raw:?}: {reason}")]
+pub struct VersionError {
+    pub raw: String,
+    pub reason: String,
+}
+
+impl Diagnostic for VersionError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("baryon::version::invalid"))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(VERSION_RULES))
+    }
+}
+
+/// Read a release version, mapping legacy forms like `1.2`, `v0.3` or `1.0beta` onto semver as
+/// described in [`VERSION_RULES`]. Valid semver is returned unchanged.
+pub fn parse_version(raw: &str) -> Result<Version, VersionError> {
+    if let Ok(version) = Version::parse(raw) {
+        return Ok(version);
+    }
+    let error = |reason: &str| VersionError {
+        raw: raw.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let text = raw.trim();
+    let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+    let (text, build) = text.split_once('+').unwrap_or((text, ""));
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (numbers, tag) = text.split_at(split);
+    // A dot may separate the numbers from the tag, as in `1.0.beta`.
+    let numbers = match numbers.strip_suffix('.') {
+        Some(numbers) if !tag.is_empty() => numbers,
+        _ => numbers,
+    };
+
+    let numbers = numbers
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| error("expected numbers separated by dots"))?;
+    if numbers.len() > 3 {
+        return Err(error("it has more than three numbers"));
+    }
+    let number = |i: usize| numbers.get(i).copied().unwrap_or(0);
+
+    let tag = tag.strip_prefix(['-', '.']).unwrap_or(tag);
+    let mut identifiers = Vec::new();
+    for part in tag.split(['.', '-']).filter(|_| !tag.is_empty()) {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(error(
+                "the prerelease has characters other than letters and digits",
+            ));
+        }
+        identifiers.extend(split_alphanumeric(part));
+    }
+
+    Ok(Version {
+        major: number(0),
+        minor: number(1),
+        patch: number(2),
+        pre: Prerelease::new(&identifiers.join("."))
+            .map_err(|e| error(&format!("the prerelease is invalid: {}", e)))?,
+        build: BuildMetadata::new(build)
+            .map_err(|e| error(&format!("the build metadata is invalid: {}", e)))?,
+    })
+}
+
+/// Split where letters and digits meet, dropping leading zeros from numbers: `b02` -> `b`, `2`.
+fn split_alphanumeric(part: &str) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    for c in part.chars() {
+        match pieces.last_mut() {
+            Some(last) if last.ends_with(|l: char| l.is_ascii_digit()) == c.is_ascii_digit() => {
+                last.push(c)
+            }
+            _ => pieces.push(c.to_string()),
+        }
+    }
+    pieces
+        .into_iter()
+        .map(|piece| match piece.parse::<u64>() {
+            Ok(number) => number.to_string(),
+            Err(_) => piece,
+        })
+        .collect()
+}
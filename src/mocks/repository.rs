@@ -1,4 +1,5 @@
-use crate::core::repository::Repository;
+use crate::core::dependencies::InvalidRelease;
+use crate::core::repository::{read_index, Repository};
 use crate::specs::{Package, Repository as RepositorySpec};
 use serde_yaml;
 use tokio::fs;
@@ -6,6 +7,7 @@ use tokio::io::AsyncReadExt;
 
 pub struct MockRepository {
     pub packages: RepositorySpec,
+    pub invalid: Vec<InvalidRelease>,
 }
 
 impl MockRepository {
@@ -18,9 +20,12 @@ impl MockRepository {
             .await
             .unwrap();
 
-        Self {
-            packages: serde_yaml::from_str(&dst).unwrap(),
-        }
+        Self::from_yaml(&dst)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Self {
+        let (packages, invalid) = read_index(serde_yaml::from_str(yaml).unwrap()).unwrap();
+        Self { packages, invalid }
     }
 }
 
@@ -32,4 +37,8 @@ impl Repository for MockRepository {
     fn get_packages(&self) -> Vec<&Package> {
         self.packages.values().collect()
     }
+
+    fn invalid_releases(&self) -> Vec<&InvalidRelease> {
+        self.invalid.iter().collect()
+    }
 }
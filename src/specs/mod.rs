@@ -1,3 +1,42 @@
 use typify::import_types;
 
 import_types!(schema = "src/specs/spec.json",);
+
+impl Dependency {
+    /// The version requirement, whichever platforms it applies to.
+    pub fn requirement(&self) -> &str {
+        match self {
+            Dependency::Requirement(requirement) => requirement,
+            Dependency::TargetedRequirement(targeted) => &targeted.version,
+        }
+    }
+}
+
+/// The requirement, followed by the platforms it is limited to, e.g. `^1.0 (os: linux)`.
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependency::Requirement(requirement) => write!(f, "{}", requirement.as_str()),
+            Dependency::TargetedRequirement(targeted) => {
+                let mut limits = Vec::new();
+                if !targeted.os.is_empty() {
+                    limits.push(format!("os: {}", targeted.os.join(", ")));
+                }
+                if !targeted.arch.is_empty() {
+                    limits.push(format!("arch: {}", targeted.arch.join(", ")));
+                }
+                match limits.is_empty() {
+                    true => write!(f, "{}", targeted.version.as_str()),
+                    false => write!(f, "{} ({})", targeted.version.as_str(), limits.join("; ")),
+                }
+            }
+        }
+    }
+}
+
+/// Types for project manifests, kept apart from the repository types whose names they share.
+pub mod manifest {
+    use typify::import_types;
+
+    import_types!(schema = "src/specs/manifest.json",);
+}
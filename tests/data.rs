@@ -47,14 +47,22 @@ mod data {
 }
 
 mod resolution {
-    use baryon::core::dependencies::{PackageRequirement, PackageResolver, Repository, Strategy};
-    use baryon::core::repository::Repository as Repo;
+    use baryon::core::dependencies::{
+        AbortReason, PackageRequirement, PackageResolver, Repository, Resolution, ResolveError,
+        ResolveLimits, Strategy,
+    };
+    use baryon::core::installed::package_path;
+    use baryon::core::repository::{find_release, Repository as Repo};
+    use baryon::core::supercollider;
+    use baryon::core::target::Target;
     use baryon::mocks::repository::MockRepository;
+    use semver::Version;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_package_resolution() {
         let mock = MockRepository::new().await;
-        let repo = Repository::new(mock.get_packages());
+        let repo = Repository::new(&mock, false).unwrap();
 
         let strategy = Strategy::new();
         let requirements =
@@ -73,4 +81,2732 @@ mod resolution {
             })
             .unwrap_or_default()
     }
+
+    const STRATEGIES: &str = r#"
+app:
+  name: app
+  description: Requested directly.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib: ^1.0.0
+    - version: 1.1.0
+      url: https://homepage.org/app/versions/1.1.0
+      dependencies:
+        lib: ^1.0.0
+lib:
+  name: lib
+  description: Only a dependency.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+    - version: 1.1.0
+      url: https://homepage.org/lib/versions/1.1.0
+    - version: 1.2.0
+      url: https://homepage.org/lib/versions/1.2.0
+"#;
+
+    fn resolve_with(strategy: Strategy) -> Vec<(String, String)> {
+        let mock = MockRepository::from_yaml(STRATEGIES);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements =
+            vec![PackageRequirement::new("app".to_string(), "*".to_string()).unwrap()];
+
+        let mut resolver = PackageResolver::new(requirements, repo, strategy);
+        let mut selected = resolver
+            .resolve()
+            .unwrap()
+            .values()
+            .map(|pv| (pv.name.clone(), pv.version.to_string()))
+            .collect::<Vec<_>>();
+        selected.sort();
+        selected
+    }
+
+    fn versions(app: &str, lib: &str) -> Vec<(String, String)> {
+        vec![
+            ("app".to_string(), app.to_string()),
+            ("lib".to_string(), lib.to_string()),
+        ]
+    }
+
+    #[test]
+    fn resolves_highest_versions() {
+        let strategy = Strategy::with_resolution(Resolution::Highest);
+        assert_eq!(resolve_with(strategy), versions("1.1.0", "1.2.0"));
+    }
+
+    #[test]
+    fn resolves_lowest_versions() {
+        let strategy = Strategy::with_resolution(Resolution::Lowest);
+        assert_eq!(resolve_with(strategy), versions("1.0.0", "1.0.0"));
+    }
+
+    #[test]
+    fn resolves_lowest_direct_versions() {
+        let strategy = Strategy::with_resolution(Resolution::LowestDirect);
+        assert_eq!(resolve_with(strategy), versions("1.0.0", "1.2.0"));
+    }
+
+    #[test]
+    fn resolves_with_minimal_change() {
+        let current = |versions: &[(&str, &str)]| {
+            versions
+                .iter()
+                .map(|(name, version)| (name.to_string(), semver::Version::parse(version).unwrap()))
+                .collect()
+        };
+        let strategy = |versions: &[(&str, &str)]| {
+            Strategy::with_resolution(Resolution::MinimalChange).current(current(versions))
+        };
+
+        // Current versions are kept, anything new gets the newest version.
+        assert_eq!(
+            resolve_with(strategy(&[("app", "1.0.0"), ("lib", "1.1.0")])),
+            versions("1.0.0", "1.1.0")
+        );
+        assert_eq!(
+            resolve_with(strategy(&[("app", "1.0.0")])),
+            versions("1.0.0", "1.2.0")
+        );
+        // A current version that is gone moves to the closest newer one.
+        assert_eq!(
+            resolve_with(strategy(&[("lib", "1.0.5")])),
+            versions("1.1.0", "1.1.0")
+        );
+    }
+
+    #[test]
+    fn parses_resolution_names() {
+        for resolution in [
+            Resolution::Highest,
+            Resolution::Lowest,
+            Resolution::LowestDirect,
+            Resolution::MinimalChange,
+        ] {
+            assert_eq!(resolution.to_string().parse::<Resolution>(), Ok(resolution));
+        }
+        assert!("newest".parse::<Resolution>().is_err());
+    }
+
+    const INVALID: &str = r#"
+package1:
+  name: package1
+  description: One good release, two bad ones.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package1
+  repo: https://github.com/person/package1
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/package1/versions/1.0.0
+    - version: 1.1.0.1
+      url: https://homepage.org/package1/versions/1.1.0.1
+    - version: 1.2.0
+      url: https://homepage.org/package1/versions/1.2.0
+      dependencies:
+        package2: ^1.0.0-01
+"#;
+
+    #[test]
+    fn skips_invalid_releases() {
+        let mock = MockRepository::from_yaml(INVALID);
+        let repo = Repository::new(&mock, false).unwrap();
+
+        let versions = repo
+            .get_versions("package1")
+            .into_iter()
+            .map(|pv| pv.version.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["1.0.0"]);
+
+        let skipped = repo
+            .skipped
+            .iter()
+            .map(|r| (r.release.as_str(), r.field.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![("1.1.0.1", "version"), ("1.2.0", "dependencies.package2")]
+        );
+    }
+
+    #[test]
+    fn skips_packages_named_like_paths() {
+        let mock = MockRepository::from_yaml(&INVALID.replace("package1", "../package1"));
+        let repo = Repository::new(&mock, false).unwrap();
+        assert!(repo.get_versions("../package1").is_empty());
+        assert!(repo.skipped.iter().any(|r| r.field == "name"));
+
+        let root = std::path::Path::new("repository");
+        assert!(package_path(root, "../package1").is_err());
+        assert!(package_path(root, "/package1").is_err());
+        assert!(package_path(root, "..").is_err());
+        assert_eq!(
+            package_path(root, "package1").unwrap(),
+            root.join("package1")
+        );
+    }
+
+    #[test]
+    fn keeps_legacy_versions_for_display() {
+        let yaml = r#"
+package1:
+  name: package1
+  description: Uses quark-style versions.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package1
+  repo: https://github.com/person/package1
+  releases:
+    - version: "1.0"
+      url: https://homepage.org/package1/versions/1.0
+    - version: 1.1beta
+      url: https://homepage.org/package1/versions/1.1beta
+"#;
+        let mock = MockRepository::from_yaml(yaml);
+        let repo = Repository::new(&mock, true).unwrap();
+
+        let mut versions = repo
+            .get_versions("package1")
+            .into_iter()
+            .map(|pv| pv.version)
+            .collect::<Vec<_>>();
+        versions.sort();
+        assert_eq!(
+            versions,
+            vec![
+                semver::Version::new(1, 0, 0),
+                semver::Version::parse("1.1.0-beta").unwrap(),
+            ]
+        );
+        assert_eq!(repo.display_version("package1", &versions[1]), "1.1beta");
+    }
+
+    #[test]
+    fn rejects_legacy_versions_that_collide() {
+        let yaml = r#"
+package1:
+  name: package1
+  description: Has two releases that read as the same version.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package1
+  repo: https://github.com/person/package1
+  releases:
+    - version: "1.0"
+      url: https://homepage.org/package1/versions/1.0
+    - version: 1.0.0
+      url: https://homepage.org/package1/versions/1.0.0
+      dependencies:
+        package2: ^1.0
+    - version: 1.1.0
+      url: https://homepage.org/package1/versions/1.1.0
+"#;
+        let mock = MockRepository::from_yaml(yaml);
+
+        // Neither release is used, as it is unclear which one 1.0.0 means.
+        let repo = Repository::new(&mock, false).unwrap();
+        let versions = repo
+            .get_versions("package1")
+            .into_iter()
+            .map(|pv| pv.version.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["1.1.0"]);
+        let skipped = repo
+            .skipped
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                "package1 1.0: invalid version \"1.0\": reads as 1.0.0, as does \"1.0.0\"",
+                "package1 1.0.0: invalid version \"1.0.0\": reads as 1.0.0, as does \"1.0\"",
+            ]
+        );
+        assert!(repo.skipped[0]
+            .help
+            .as_deref()
+            .is_some_and(|help| help.contains("missing minor and patch numbers are 0")));
+        let package = mock.get_package("package1").unwrap();
+        assert!(find_release(package, &semver::Version::new(1, 0, 0)).is_none());
+
+        let Err(invalid) = Repository::new(&mock, true) else {
+            panic!("a strict index should be rejected");
+        };
+        assert_eq!(invalid.releases.len(), 2);
+    }
+
+    #[test]
+    fn reports_every_invalid_release_when_strict() {
+        let mock = MockRepository::from_yaml(INVALID);
+        let Err(invalid) = Repository::new(&mock, true) else {
+            panic!("a strict index should be rejected");
+        };
+
+        assert_eq!(
+            invalid.to_string(),
+            "The package index has 2 invalid releases"
+        );
+        assert!(invalid.releases[1]
+            .to_string()
+            .starts_with("package1 1.2.0: invalid dependencies.package2 \"^1.0.0-01\""));
+    }
+
+    const RANGES: &str = r#"
+app:
+  name: app
+  description: Depends on lib through a range.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib: ^1.2 || ^2.0
+    - version: 2.0.0
+      url: https://homepage.org/app/versions/2.0.0
+      dependencies:
+        lib: 1.2.0 - 1.x
+lib:
+  name: lib
+  description: Has releases in and out of the ranges.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+    - version: 1.5.0
+      url: https://homepage.org/lib/versions/1.5.0
+    - version: 2.1.0
+      url: https://homepage.org/lib/versions/2.1.0
+    - version: 3.0.0
+      url: https://homepage.org/lib/versions/3.0.0
+"#;
+
+    #[test]
+    fn resolves_or_and_hyphen_ranges() {
+        let resolve = |app: &str| {
+            let mock = MockRepository::from_yaml(RANGES);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements =
+                vec![PackageRequirement::new("app".to_string(), app.to_string()).unwrap()];
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+            resolver.resolve().unwrap()["lib"].version.to_string()
+        };
+
+        assert_eq!(resolve("=1.0.0"), "2.1.0");
+        assert_eq!(resolve("=2.0.0"), "1.5.0");
+    }
+
+    const FEATURES: &str = r#"
+app:
+  name: app
+  description: Needs the gui feature of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib[gui]: ^1.0
+plugin:
+  name: plugin
+  description: Needs the midi feature of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/plugin
+  repo: https://github.com/person/plugin
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/plugin/versions/1.0.0
+      dependencies:
+        lib[midi]: ^1.0
+lib:
+  name: lib
+  description: Has optional dependencies behind features.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+      features:
+        gui:
+          widgets: ^1.0
+        midi:
+          portmidi: ^2.0
+        extra:
+          heavy: ^1.0
+widgets:
+  name: widgets
+  description: Optional dependency of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/widgets
+  repo: https://github.com/person/widgets
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/widgets/versions/1.0.0
+portmidi:
+  name: portmidi
+  description: Optional dependency of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/portmidi
+  repo: https://github.com/person/portmidi
+  releases:
+    - version: 2.0.0
+      url: https://homepage.org/portmidi/versions/2.0.0
+heavy:
+  name: heavy
+  description: Optional dependency of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/heavy
+  repo: https://github.com/person/heavy
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/heavy/versions/1.0.0
+"#;
+
+    #[test]
+    fn pulls_in_requested_features_only() {
+        let resolve = |names: &[&str]| {
+            let mock = MockRepository::from_yaml(FEATURES);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements = names
+                .iter()
+                .flat_map(|name| {
+                    PackageRequirement::with_features(name.to_string(), "*".to_string()).unwrap()
+                })
+                .collect();
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+            let selected = resolver.resolve().unwrap();
+            let mut names = selected.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            (names, selected["lib"].features.clone())
+        };
+
+        assert_eq!(resolve(&["lib"]), (vec!["lib".to_string()], vec![]));
+        assert_eq!(
+            resolve(&["app", "plugin"]),
+            (
+                vec!["app", "lib", "plugin", "portmidi", "widgets"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                vec!["gui".to_string(), "midi".to_string()]
+            )
+        );
+        assert_eq!(
+            resolve(&["lib[extra, gui]"]).0,
+            vec!["heavy", "lib", "widgets"]
+        );
+    }
+
+    const PLATFORMS: &str = r#"
+app:
+  name: app
+  description: Has platform-specific dependencies.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        common: ^1.0
+        alsa:
+          version: ^1.0
+          os: [linux]
+        coreaudio:
+          version: ^1.0
+          os: [macos]
+          arch: [aarch64]
+common:
+  name: common
+  description: Needed everywhere.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/common
+  repo: https://github.com/person/common
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/common/versions/1.0.0
+alsa:
+  name: alsa
+  description: Linux only.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/alsa
+  repo: https://github.com/person/alsa
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/alsa/versions/1.0.0
+coreaudio:
+  name: coreaudio
+  description: Apple silicon only.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/coreaudio
+  repo: https://github.com/person/coreaudio
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/coreaudio/versions/1.0.0
+"#;
+
+    #[test]
+    fn picks_dependencies_for_the_target() {
+        let resolve = |target: &str| {
+            let mock = MockRepository::from_yaml(PLATFORMS);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements =
+                vec![PackageRequirement::new("app".to_string(), "*".to_string()).unwrap()];
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest())
+                .target(target.parse().unwrap());
+            let mut names = resolver
+                .resolve()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(resolve("linux-x86_64"), vec!["alsa", "app", "common"]);
+        assert_eq!(resolve("macos-aarch64"), vec!["app", "common", "coreaudio"]);
+        assert_eq!(resolve("macos-x86_64"), vec!["app", "common"]);
+        assert!("linux".parse::<Target>().is_err());
+    }
+
+    const SUPERCOLLIDER: &str = r#"
+quark:
+  name: quark
+  description: Needs a newer SuperCollider from 2.0.0 on.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/quark
+  repo: https://github.com/person/quark
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/quark/versions/1.0.0
+      supercollider: ">=3.10"
+    - version: 2.0.0
+      url: https://homepage.org/quark/versions/2.0.0
+      supercollider: ">=3.13"
+"#;
+
+    #[test]
+    fn picks_releases_for_the_supercollider_version() {
+        let resolve = |spec: &str, supercollider: Option<&str>| {
+            let mock = MockRepository::from_yaml(SUPERCOLLIDER);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements =
+                vec![PackageRequirement::new("quark".to_string(), spec.to_string()).unwrap()];
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest())
+                .supercollider(supercollider.map(|v| Version::parse(v).unwrap()));
+            let selected = resolver.resolve()?;
+            assert_eq!(selected.len(), 1);
+            Ok(selected["quark"].version.to_string())
+        };
+
+        assert_eq!(resolve("*", Some("3.12.1")).unwrap(), "1.0.0");
+        assert_eq!(resolve("*", Some("3.13.0")).unwrap(), "2.0.0");
+        assert_eq!(resolve("*", None).unwrap(), "2.0.0");
+
+        let Err(ResolveError::Conflict(report)) = resolve("=2.0.0", Some("3.12.1")) else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(report.package, supercollider::PACKAGE);
+        assert_eq!(
+            report.to_string(),
+            "quark 2.0.0 requires SuperCollider >=3.13, but SuperCollider is 3.12.1"
+        );
+        assert_eq!(
+            supercollider::version_in("sclang 3.13.0 (Built from branch 'Version-3.13.0')"),
+            Version::parse("3.13.0").ok()
+        );
+    }
+
+    #[test]
+    fn keeps_index_packages_named_supercollider() {
+        let yaml = r#"
+app:
+  name: app
+  description: Depends on a quark that happens to be called supercollider.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      supercollider: ">=3.10"
+      dependencies:
+        supercollider: ^1.0
+supercollider:
+  name: supercollider
+  description: A quark, not SuperCollider itself.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/supercollider
+  repo: https://github.com/person/supercollider
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/supercollider/versions/1.0.0
+"#;
+        let resolve = |supercollider: Option<&str>| {
+            let mock = MockRepository::from_yaml(yaml);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements =
+                vec![PackageRequirement::new("app".to_string(), "*".to_string()).unwrap()];
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest())
+                .supercollider(supercollider.map(|v| Version::parse(v).unwrap()));
+            let mut names = resolver
+                .resolve()
+                .unwrap()
+                .values()
+                .map(|pv| format!("{} {}", pv.name, pv.version))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        assert_eq!(resolve(None), vec!["app 1.0.0", "supercollider 1.0.0"]);
+        assert_eq!(
+            resolve(Some("3.13.0")),
+            vec!["app 1.0.0", "supercollider 1.0.0"]
+        );
+    }
+
+    const ALTERNATIVES: &str = r#"
+app:
+  name: app
+  description: Depends on the old name of a renamed quark.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        oldquark: ^1.0
+oldquark:
+  name: oldquark
+  description: Renamed to newquark.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/oldquark
+  repo: https://github.com/person/oldquark
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/oldquark/versions/1.0.0
+    - version: 1.1.0
+      url: https://homepage.org/oldquark/versions/1.1.0
+newquark:
+  name: newquark
+  description: The renamed oldquark.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/newquark
+  repo: https://github.com/person/newquark
+  releases:
+    - version: 2.0.0
+      url: https://homepage.org/newquark/versions/2.0.0
+      replaces:
+        oldquark: 1.2.0
+synth:
+  name: synth
+  description: Needs a MIDI backend, but clashes with rtmidi.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/synth
+  repo: https://github.com/person/synth
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/synth/versions/1.0.0
+      dependencies:
+        midi-backend: ^1.0
+      conflicts:
+        rtmidi: "*"
+portmidi:
+  name: portmidi
+  description: A MIDI backend.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/portmidi
+  repo: https://github.com/person/portmidi
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/portmidi/versions/1.0.0
+      provides:
+        midi-backend: 1.0.0
+rtmidi:
+  name: rtmidi
+  description: Another MIDI backend.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/rtmidi
+  repo: https://github.com/person/rtmidi
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/rtmidi/versions/1.0.0
+      provides:
+        midi-backend: 1.0.0
+"#;
+
+    #[test]
+    fn enforces_conflicts_provides_and_replaces() {
+        let resolve = |requested: &[(&str, &str)]| {
+            let mock = MockRepository::from_yaml(ALTERNATIVES);
+            let repo = Repository::new(&mock, true).unwrap();
+            let requirements = requested
+                .iter()
+                .map(|(name, spec)| {
+                    PackageRequirement::new(name.to_string(), spec.to_string()).unwrap()
+                })
+                .collect();
+            let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+            resolver.resolve().map(|selected| {
+                let mut names = selected
+                    .values()
+                    .map(|pv| format!("{} {}", pv.name, pv.version))
+                    .collect::<Vec<_>>();
+                names.sort();
+                names
+            })
+        };
+
+        assert_eq!(
+            resolve(&[("app", "*")]).unwrap(),
+            vec!["app 1.0.0", "newquark 2.0.0"]
+        );
+        assert_eq!(
+            resolve(&[("app", "*"), ("oldquark", "=1.1.0")]).unwrap(),
+            vec!["app 1.0.0", "oldquark 1.1.0"]
+        );
+        assert_eq!(
+            resolve(&[("synth", "*")]).unwrap(),
+            vec!["portmidi 1.0.0", "synth 1.0.0"]
+        );
+
+        let Err(ResolveError::Conflict(report)) =
+            resolve(&[("newquark", "*"), ("oldquark", "=1.0.0")])
+        else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(
+            report.to_string(),
+            "oldquark =1.0.0 was requested, but newquark 2.0.0 conflicts with oldquark *"
+        );
+    }
+
+    #[test]
+    fn forced_versions_meet_every_requirement() {
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml").unwrap();
+        let mock = MockRepository::from_yaml(&yaml);
+        let resolve = |forced: &[(&str, Version)]| {
+            let forced = forced
+                .iter()
+                .map(|(name, version)| (name.to_string(), version.clone()))
+                .collect();
+            let repo = Repository::new(&mock, true)
+                .unwrap()
+                .with_forced_versions(&forced);
+            let requirement =
+                PackageRequirement::new("package1".to_string(), "=1.2.0".to_string()).unwrap();
+            let mut resolver = PackageResolver::new(vec![requirement], repo, Strategy::latest());
+            let selected = resolver.resolve().unwrap();
+            let mut names = selected
+                .values()
+                .map(|pv| format!("{} {}", pv.name, pv.version))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        // package1 1.2.0 depends on package2 0.0.1, unless package2 is forced to 0.0.2, which
+        // brings its own dependencies instead.
+        assert_eq!(
+            resolve(&[]),
+            vec!["package1 1.2.0", "package2 0.0.1", "package3 0.2.2"]
+        );
+        assert_eq!(
+            resolve(&[("package2", Version::new(0, 0, 2))]),
+            vec!["package1 1.2.0", "package2 0.0.2"]
+        );
+    }
+
+    #[test]
+    fn forced_versions_apply_to_features() {
+        use baryon::actions::install::apply_overrides;
+        use baryon::core::settings::PackageOverride;
+        use std::collections::BTreeMap;
+
+        let yaml = r#"
+app:
+  name: app
+  description: Needs the gui feature of a newer lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib[gui]: ^1.1
+lib:
+  name: lib
+  description: Has a feature in every release.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+      features:
+        gui:
+          widgets: ^1.0
+    - version: 1.1.0
+      url: https://homepage.org/lib/versions/1.1.0
+      features:
+        gui:
+          widgets: ^2.0
+widgets:
+  name: widgets
+  description: Optional dependency of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/widgets
+  repo: https://github.com/person/widgets
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/widgets/versions/1.0.0
+    - version: 2.0.0
+      url: https://homepage.org/widgets/versions/2.0.0
+"#;
+        let mock = MockRepository::from_yaml(yaml);
+        let overrides = |version: &str| {
+            BTreeMap::from([(
+                "lib".to_string(),
+                PackageOverride {
+                    version: Some(version.to_string()),
+                    ..Default::default()
+                },
+            )])
+        };
+
+        // The override wins over requirements on the package's features, too.
+        let mut requested = BTreeMap::from([("app".to_string(), "*".to_string())]);
+        let index = Repository::new(&mock, true).unwrap();
+        let Ok(index) = apply_overrides(index, &mut requested, &overrides("1.0.0")) else {
+            panic!("expected the override to apply");
+        };
+        let requirement = PackageRequirement::new("app".to_string(), "*".to_string()).unwrap();
+        let mut resolver = PackageResolver::new(vec![requirement], index, Strategy::latest());
+        let selected = resolver.resolve().unwrap();
+        assert_eq!(selected["lib"].version, Version::new(1, 0, 0));
+        assert_eq!(selected["widgets"].version, Version::new(1, 0, 0));
+
+        // A version override with nowhere to install from must name an indexed release.
+        let index = Repository::new(&mock, true).unwrap();
+        assert!(apply_overrides(index, &mut requested, &overrides("2.0.0")).is_err());
+    }
+
+    const CONFLICTING: &str = r#"
+package1:
+  name: package1
+  description: Needs an old package3.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package1
+  repo: https://github.com/person/package1
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/package1/versions/1.0.0
+      dependencies:
+        package3: =0.2.2
+package3:
+  name: package3
+  description: Depended on by both.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package3
+  repo: https://github.com/person/package3
+  releases:
+    - version: 0.2.2
+      url: https://homepage.org/package3/versions/0.2.2
+    - version: 0.3.1
+      url: https://homepage.org/package3/versions/0.3.1
+package4:
+  name: package4
+  description: Needs a new package3.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package4
+  repo: https://github.com/person/package4
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/package4/versions/1.0.0
+      dependencies:
+        package3: ^0.3.0
+"#;
+
+    #[test]
+    fn reports_conflicting_requirements() {
+        let mock = MockRepository::from_yaml(CONFLICTING);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements = vec![
+            PackageRequirement::new("package1".to_string(), "=1.0.0".to_string()).unwrap(),
+            PackageRequirement::new("package4".to_string(), "*".to_string()).unwrap(),
+        ];
+
+        let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+        let Err(ResolveError::Conflict(report)) = resolver.resolve() else {
+            panic!("resolution should fail");
+        };
+
+        assert_eq!(report.package, "package3");
+        assert_eq!(report.requirements.len(), 2);
+        assert_eq!(
+            report.to_string(),
+            "package1 1.0.0 requires package3 =0.2.2, but package4 1.0.0 requires package3 ^0.3.0"
+        );
+        assert_eq!(report.tried, vec![semver::Version::new(0, 2, 2)]);
+    }
+
+    const BROKEN_NEWEST: &str = r#"
+a:
+  name: a
+  description: Newest release needs a broken b.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/a
+  repo: https://github.com/person/a
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/a/versions/1.0.0
+    - version: 2.0.0
+      url: https://homepage.org/a/versions/2.0.0
+      dependencies:
+        b: ^2.0.0
+        c: ^1.0.0
+b:
+  name: b
+  description: Depends on a package that does not exist.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/b
+  repo: https://github.com/person/b
+  releases:
+    - version: 2.0.0
+      url: https://homepage.org/b/versions/2.0.0
+      dependencies:
+        missing: ^1.0.0
+c:
+  name: c
+  description: Fine on its own.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/c
+  repo: https://github.com/person/c
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/c/versions/1.0.0
+"#;
+
+    #[test]
+    fn backjumps_past_unusable_versions() {
+        let mock = MockRepository::from_yaml(BROKEN_NEWEST);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements = vec![PackageRequirement::new("a".to_string(), "*".to_string()).unwrap()];
+
+        let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+        let Ok(resolved) = resolver.resolve() else {
+            panic!("resolution should succeed");
+        };
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved["a"].version, semver::Version::new(1, 0, 0));
+    }
+
+    fn resolve_within(limits: ResolveLimits) -> ResolveError {
+        let mock = MockRepository::from_yaml(BROKEN_NEWEST);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements = vec![PackageRequirement::new("a".to_string(), "*".to_string()).unwrap()];
+
+        let mut resolver =
+            PackageResolver::new(requirements, repo, Strategy::latest()).limits(limits);
+        resolver.resolve().expect_err("resolution should give up")
+    }
+
+    #[test]
+    fn gives_up_after_the_step_budget() {
+        let ResolveError::Aborted(aborted) = resolve_within(ResolveLimits {
+            max_steps: Some(1),
+            timeout: None,
+        }) else {
+            panic!("resolution should be aborted");
+        };
+
+        assert_eq!(aborted.reason, AbortReason::Steps(1));
+        assert_eq!(
+            aborted.to_string(),
+            "Gave up resolving after 1 backtracking steps"
+        );
+        // b has no usable version, which is what sent the solver back.
+        assert_eq!(aborted.contended[0].0, "b");
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let ResolveError::Aborted(aborted) = resolve_within(ResolveLimits {
+            max_steps: None,
+            timeout: Some(Duration::ZERO),
+        }) else {
+            panic!("resolution should be aborted");
+        };
+
+        assert!(matches!(aborted.reason, AbortReason::Timeout(_)));
+    }
+
+    #[test]
+    fn explains_how_the_conflict_was_derived() {
+        let mock = MockRepository::from_yaml(CONFLICTING);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements = vec![
+            PackageRequirement::new("package1".to_string(), "*".to_string()).unwrap(),
+            PackageRequirement::new("package4".to_string(), "*".to_string()).unwrap(),
+        ];
+
+        let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+        let Err(ResolveError::Conflict(report)) = resolver.resolve() else {
+            panic!("resolution should fail");
+        };
+
+        let last = report.explanation.last().unwrap();
+        assert!(last.ends_with("version solving failed."), "{}", last);
+        assert!(report
+            .explanation
+            .iter()
+            .any(|line| line.contains("package4 1.0.0 depends on package3 ^0.3.0")));
+    }
+
+    #[test]
+    fn reports_missing_packages() {
+        let mock = MockRepository::from_yaml(CONFLICTING);
+        let repo = Repository::new(&mock, false).unwrap();
+        let requirements =
+            vec![PackageRequirement::new("package9".to_string(), "*".to_string()).unwrap()];
+
+        let mut resolver = PackageResolver::new(requirements, repo, Strategy::latest());
+        let Err(ResolveError::Conflict(report)) = resolver.resolve() else {
+            panic!("resolution should fail");
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "package9 * was requested, but no package named package9 exists"
+        );
+    }
+}
+
+mod remote {
+    use baryon::core::dependencies::{Repository as Index, ResolveLimits};
+    use baryon::core::http::CacheSettings;
+    use baryon::core::repository::{HTTPRepository, Repository};
+    use baryon::core::settings::Settings;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn can_load_repository_from_cache() {
+        let root = std::env::temp_dir().join("baryon-test-remote-cache");
+        let _ = std::fs::remove_dir_all(&root);
+        let cache_path = root.join("cache");
+        std::fs::create_dir_all(&cache_path).unwrap();
+
+        // Wrap the mock repository in the top-level `repository` key, as served by an index.
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml").unwrap();
+        let packages: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+        let index = serde_json::json!({ "repository": packages });
+        std::fs::write(cache_path.join("repository.json"), index.to_string()).unwrap();
+
+        let settings = Settings {
+            global_repository_path: root.join("repository").to_string_lossy().to_string(),
+            repository_url: "http://localhost:0/repo.json".to_string(),
+            repositories: vec![],
+            pins: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            cache_settings: CacheSettings {
+                cache_path: cache_path.to_string_lossy().to_string(),
+                cache_timeout: Duration::from_secs(3600),
+            },
+            resolve_limits: ResolveLimits::default(),
+            strict_repository: false,
+            expand_manifest_tokens: false,
+            supercollider_version: None,
+        };
+
+        let repo = HTTPRepository::load(&settings).await.unwrap();
+        let mut package_names = repo
+            .get_packages()
+            .iter()
+            .map(|package| package.name.clone())
+            .collect::<Vec<String>>();
+        package_names.sort();
+
+        assert_eq!(package_names, vec!["package1", "package2", "package3"]);
+        assert!(repo.get_package("package2").is_some());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn loads_index_with_invalid_releases() {
+        let root = std::env::temp_dir().join("baryon-test-remote-invalid");
+        let _ = std::fs::remove_dir_all(&root);
+        let cache_path = root.join("cache");
+        std::fs::create_dir_all(&cache_path).unwrap();
+
+        let index = serde_json::json!({ "repository": {
+            "package1": {
+                "name": "package1",
+                "description": "One good release, five bad ones.",
+                "authors": ["person"],
+                "license": "MIT",
+                "url": "https://homepage.org/package1",
+                "repo": "https://github.com/person/package1",
+                "releases": [
+                    { "version": "1.0.0", "url": "https://homepage.org/package1/versions/1.0.0" },
+                    { "version": "latest", "url": "https://homepage.org/package1/versions/latest" },
+                    {
+                        "version": "1.2.0",
+                        "url": "https://homepage.org/package1/versions/1.2.0",
+                        "dependencies": { "package2": "whatever" }
+                    },
+                    {
+                        "version": "1.3.0",
+                        "url": "https://homepage.org/package1/versions/1.3.0",
+                        "dependencies": { "package2": "^1.0", "bad.name": "^1.0" }
+                    },
+                    {
+                        "version": "1.4.0",
+                        "url": "https://homepage.org/package1/versions/1.4.0",
+                        "dependencies": { "package2": { "version": "^1.0", "os": "linux" } }
+                    },
+                    {
+                        "version": "1.5.0",
+                        "url": "https://homepage.org/package1/versions/1.5.0",
+                        "yanked": "yes"
+                    }
+                ]
+            }
+        }});
+        std::fs::write(cache_path.join("repository.json"), index.to_string()).unwrap();
+
+        let settings = Settings {
+            global_repository_path: root.join("repository").to_string_lossy().to_string(),
+            repository_url: "http://localhost:0/repo.json".to_string(),
+            repositories: vec![],
+            pins: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            cache_settings: CacheSettings {
+                cache_path: cache_path.to_string_lossy().to_string(),
+                cache_timeout: Duration::from_secs(3600),
+            },
+            resolve_limits: ResolveLimits::default(),
+            strict_repository: false,
+            expand_manifest_tokens: false,
+            supercollider_version: None,
+        };
+        let repo = HTTPRepository::load(&settings).await.unwrap();
+
+        let index = Index::new(&repo, false).unwrap();
+        let skipped = index
+            .skipped
+            .iter()
+            .map(|r| (r.release.as_str(), r.field.as_str()))
+            .collect::<Vec<_>>();
+        // Releases that do not match the schema are reported alongside the others.
+        assert_eq!(
+            skipped,
+            vec![
+                ("1.3.0", "dependencies.bad.name"),
+                ("1.4.0", "dependencies.package2"),
+                ("1.5.0", "yanked"),
+                ("latest", "version"),
+                ("1.2.0", "dependencies.package2")
+            ]
+        );
+        assert_eq!(index.get_versions("package1").len(), 1);
+        assert!(Index::new(&repo, true).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
+
+mod list {
+    use baryon::actions::list::{run, Filter, Parameters};
+    use baryon::mocks::repository::MockRepository;
+
+    fn parameters(repository_path: &std::path::Path, filter: Filter) -> Parameters {
+        Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            filter,
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_available_and_installed_packages() {
+        let repo = MockRepository::new().await;
+        let repository_path = std::env::temp_dir().join("baryon-test-list");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(
+            repository_path.join("installed.json"),
+            r#"{"packages": {"package1": {"name": "package1", "version": "1.0.0", "url": "https://homepage.org/package1/versions/1.0.0"}}}"#,
+        )
+        .unwrap();
+
+        let all = run(&parameters(&repository_path, Filter::All), &repo)
+            .ok()
+            .unwrap();
+        let names = all
+            .packages
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["package1", "package2", "package3"]);
+        assert_eq!(all.packages[0].latest_version.as_deref(), Some("1.2.0"));
+        assert_eq!(all.packages[0].installed_version.as_deref(), Some("1.0.0"));
+        assert!(all.packages[0].upgradable);
+
+        let installed = run(&parameters(&repository_path, Filter::Installed), &repo)
+            .ok()
+            .unwrap();
+        assert_eq!(installed.packages.len(), 1);
+
+        let available = run(&parameters(&repository_path, Filter::Available), &repo)
+            .ok()
+            .unwrap();
+        assert_eq!(available.packages.len(), 2);
+
+        let upgradable = run(&parameters(&repository_path, Filter::Upgradable), &repo)
+            .ok()
+            .unwrap();
+        assert_eq!(upgradable.packages[0].name, "package1");
+        let _ = std::fs::remove_dir_all(&repository_path);
+    }
+}
+
+mod install {
+    use baryon::actions::install::{run, PackageSpec, Parameters};
+    use baryon::core::dependencies::Resolution;
+    use baryon::core::installed::InstalledPackages;
+    use baryon::core::lockfile::{Lockfile, LOCK_FILE};
+    use baryon::core::manifest::MANIFEST_FILE;
+    use baryon::core::settings::PackageOverride;
+    use baryon::mocks::repository::MockRepository;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    /// Write a gzipped tarball holding `<name>/<name>.sc`, wrapped in a top-level directory.
+    fn write_archive(path: &Path, name: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let file = std::fs::File::create(path).unwrap();
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let contents = format!("{} {{}}", name);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("{}-main/{}.sc", name, name),
+                contents.as_bytes(),
+            )
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    /// A mock repository whose release urls point at local archives under `root`.
+    fn local_repository(root: &Path) -> MockRepository {
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml").unwrap();
+        local_index(root, &yaml)
+    }
+
+    /// Like [`local_repository`], for the index in `yaml`.
+    fn local_index(root: &Path, yaml: &str) -> MockRepository {
+        let base = format!("file://{}/", root.display());
+        let repo = MockRepository::from_yaml(&yaml.replace("https://homepage.org/", &base));
+        for package in repo.packages.values() {
+            for release in package.releases.iter() {
+                let path = release.url.strip_prefix("file://").unwrap();
+                write_archive(Path::new(path), &package.name);
+            }
+        }
+        repo
+    }
+
+    #[tokio::test]
+    async fn installs_package_with_dependencies() {
+        let root = std::env::temp_dir().join("baryon-test-install");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let repository_path = root.join("repository");
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![PackageSpec::parse("package1@1.0.0")],
+            lockfile: None,
+            locked: false,
+            manifest: None,
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+        let result = run(&params, &repo).await.ok().unwrap();
+
+        let names = result
+            .installed
+            .iter()
+            .map(|row| row.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["package1", "package2", "package3"]);
+        assert!(repository_path.join("package1/package1.sc").exists());
+
+        let installed = InstalledPackages::load(&repository_path).unwrap();
+        let package1 = installed.get("package1").unwrap();
+        assert_eq!(package1.version.to_string(), "1.2.0");
+        assert_eq!(package1.requirement.as_deref(), Some("1.0.0"));
+        let package3 = installed.get("package3").unwrap();
+        assert_eq!(package3.requirement, None);
+        assert_eq!(package3.required_by[0], "package1");
+
+        // Installing again leaves everything in place.
+        let result = run(&params, &repo).await.ok().unwrap();
+        assert!(result.installed.is_empty());
+        assert_eq!(result.unchanged.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn installs_everything_the_manifest_declares() {
+        let root = std::env::temp_dir().join("baryon-test-install-manifest");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let manifest_path = root.join("project").join(MANIFEST_FILE);
+        std::fs::create_dir_all(manifest_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &manifest_path,
+            "name: project\ndependencies:\n  package1: ^1.0.0\ndev-dependencies:\n  package3: ^0.2.0\n",
+        )
+        .unwrap();
+
+        let params = |repository: &str, no_dev: bool| Parameters {
+            repository_path: root.join(repository).to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![],
+            lockfile: None,
+            locked: false,
+            manifest: Some(manifest_path.to_string_lossy().to_string()),
+            no_dev,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+
+        run(&params("with-dev", false), &repo).await.ok().unwrap();
+        let installed = InstalledPackages::load(&root.join("with-dev")).unwrap();
+        assert_eq!(
+            installed.get("package1").unwrap().requirement.as_deref(),
+            Some("^1.0.0")
+        );
+        assert_eq!(
+            installed.get("package3").unwrap().requirement.as_deref(),
+            Some("^0.2.0")
+        );
+
+        run(&params("without-dev", true), &repo).await.ok().unwrap();
+        let installed = InstalledPackages::load(&root.join("without-dev")).unwrap();
+        // Still installed as a dependency of package2, but not requested by the project.
+        assert_eq!(installed.get("package3").unwrap().requirement, None);
+    }
+
+    #[tokio::test]
+    async fn installs_locked_yanked_releases_with_a_warning() {
+        let root = std::env::temp_dir().join("baryon-test-yanked");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let lock_path = root.join("project").join(LOCK_FILE);
+        let params = |repository: &str, lockfile: Option<&Path>| Parameters {
+            repository_path: root.join(repository).to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![PackageSpec::parse("package1")],
+            lockfile: lockfile.map(|path| path.to_string_lossy().to_string()),
+            locked: false,
+            manifest: None,
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+
+        run(&params("first", Some(&lock_path)), &repo)
+            .await
+            .ok()
+            .unwrap();
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml")
+            .unwrap()
+            .replace(
+                "https://homepage.org/",
+                &format!("file://{}/", root.join("archives").display()),
+            )
+            .replace(
+                "    - version: 1.2.0\n",
+                "    - version: 1.2.0\n      yanked: true\n      yanked_reason: loads the wrong class library\n",
+            );
+        let repo = MockRepository::from_yaml(&yaml);
+
+        // The lockfile still pins the yanked release.
+        let result = run(&params("second", Some(&lock_path)), &repo)
+            .await
+            .ok()
+            .unwrap();
+        let installed = InstalledPackages::load(&root.join("second")).unwrap();
+        assert_eq!(
+            installed.get("package1").unwrap().version.to_string(),
+            "1.2.0"
+        );
+        assert_eq!(
+            result.warnings,
+            vec!["package1 1.2.0 is yanked: loads the wrong class library"]
+        );
+
+        // A fresh resolution passes it over.
+        let result = run(&params("third", None), &repo).await.ok().unwrap();
+        let installed = InstalledPackages::load(&root.join("third")).unwrap();
+        assert_eq!(
+            installed.get("package1").unwrap().version.to_string(),
+            "1.0.0"
+        );
+        assert!(result.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn records_and_enforces_lockfile() {
+        let root = std::env::temp_dir().join("baryon-test-lockfile");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let lock_path = root.join("project").join(LOCK_FILE);
+        let params = |repository: &str, package: &str, locked: bool| Parameters {
+            repository_path: root.join(repository).to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![PackageSpec::parse(package)],
+            lockfile: Some(lock_path.to_string_lossy().to_string()),
+            locked,
+            manifest: None,
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+
+        run(&params("first", "package1@1.0.0", false), &repo)
+            .await
+            .ok()
+            .unwrap();
+        let lock = Lockfile::load(&lock_path).unwrap().unwrap();
+        let versions = lock
+            .packages
+            .values()
+            .map(|p| format!("{} {}", p.name, p.version))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            versions,
+            vec!["package1 1.2.0", "package2 0.0.1", "package3 0.2.2"]
+        );
+        let package3 = lock.get("package3").unwrap();
+        assert!(package3.checksum.as_deref().unwrap().starts_with("sha256:"));
+        assert_eq!(package3.required_by, vec!["package2"]);
+
+        // A second machine installs exactly the locked set.
+        let result = run(&params("second", "package1@1.0.0", true), &repo)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(result.installed.len(), 3);
+
+        // A requirement the lock does not satisfy fails with the difference.
+        let error = run(&params("second", "package1@=1.0.0", true), &repo)
+            .await
+            .err()
+            .unwrap();
+        let message = error.base.to_string();
+        assert!(message.contains("~ package1 1.2.0 -> 1.0.0"), "{}", message);
+        assert!(message.contains("~ package2 0.0.1 -> 0.0.2"), "{}", message);
+
+        // A release that changed after locking is refused.
+        let mut tampered = lock.clone();
+        tampered.packages.get_mut("package3").unwrap().checksum = Some("sha256:00".to_string());
+        tampered.save(&lock_path).unwrap();
+        let error = run(&params("third", "package1@1.0.0", false), &repo)
+            .await
+            .err()
+            .unwrap();
+        assert!(error
+            .base
+            .to_string()
+            .contains("Checksum mismatch for package3"));
+    }
+
+    #[test]
+    fn locks_dependencies_that_apply_on_the_target() {
+        use baryon::actions::install::lock_selection;
+        use baryon::core::dependencies::PackageVersion;
+
+        let repo = MockRepository::from_yaml(
+            r#"
+app:
+  name: app
+  description: Needs lib everywhere.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib: ^1.0
+plugin:
+  name: plugin
+  description: Needs lib on plan9 only.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/plugin
+  repo: https://github.com/person/plugin
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/plugin/versions/1.0.0
+      dependencies:
+        lib:
+          version: ^1.0
+          os: [plan9]
+lib:
+  name: lib
+  description: Depended on by both.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+"#,
+        );
+        let selected = ["app", "lib", "plugin"]
+            .into_iter()
+            .map(|name| PackageVersion {
+                name: name.to_string(),
+                version: semver::Version::new(1, 0, 0),
+                required_by: Vec::new(),
+                features: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let lock = lock_selection(
+            &selected,
+            &repo,
+            &InstalledPackages::default(),
+            None,
+            &BTreeMap::new(),
+            &"linux-x86_64".parse().unwrap(),
+        );
+        assert_eq!(lock.get("lib").unwrap().required_by, vec!["app"]);
+    }
+
+    #[tokio::test]
+    async fn locks_only_what_the_manifest_needs() {
+        let root = std::env::temp_dir().join("baryon-test-lockfile-manifest");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let manifest_path = root.join("project").join(MANIFEST_FILE);
+        let lock_path = root.join("project").join(LOCK_FILE);
+        std::fs::create_dir_all(manifest_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &manifest_path,
+            "name: project\ndependencies:\n  package3: ^0.2.0\n",
+        )
+        .unwrap();
+        let params = |packages: Vec<PackageSpec>, manifest: Option<&Path>| Parameters {
+            repository_path: root.join("repository").to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages,
+            lockfile: Some(lock_path.to_string_lossy().to_string()),
+            locked: false,
+            manifest: manifest.map(|path| path.to_string_lossy().to_string()),
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+
+        // Installed explicitly, but not by the project.
+        run(
+            &params(vec![PackageSpec::parse("package1@1.0.0")], None),
+            &repo,
+        )
+        .await
+        .ok()
+        .unwrap();
+        std::fs::remove_file(&lock_path).unwrap();
+
+        run(&params(vec![], Some(&manifest_path)), &repo)
+            .await
+            .ok()
+            .unwrap();
+        let lock = Lockfile::load(&lock_path).unwrap().unwrap();
+        let names = lock.packages.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(names, vec!["package3"]);
+
+        // Checking against the lock only compares what the project needs.
+        let locked = Parameters {
+            locked: true,
+            ..params(vec![], Some(&manifest_path))
+        };
+        run(&locked, &repo).await.ok().unwrap();
+    }
+
+    const FEATURES: &str = r#"
+app:
+  name: app
+  description: Needs the gui feature of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/app
+  repo: https://github.com/person/app
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/app/versions/1.0.0
+      dependencies:
+        lib[gui]: ^1.0
+lib:
+  name: lib
+  description: Has an optional dependency behind a feature.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/lib
+  repo: https://github.com/person/lib
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/lib/versions/1.0.0
+      dependencies:
+        elsewhere:
+          version: ^1.0
+          os: [plan9]
+      features:
+        gui:
+          widgets: ^1.0
+widgets:
+  name: widgets
+  description: Optional dependency of lib.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/widgets
+  repo: https://github.com/person/widgets
+  releases:
+    - version: 1.0.0
+      url: https://homepage.org/widgets/versions/1.0.0
+"#;
+
+    #[tokio::test]
+    async fn uninstall_keeps_and_removes_feature_dependencies() {
+        use baryon::actions::uninstall;
+
+        let root = std::env::temp_dir().join("baryon-test-install-features");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_index(&root.join("archives"), FEATURES);
+        let repository_path = root.join("repository");
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![PackageSpec::parse("app")],
+            lockfile: None,
+            locked: false,
+            manifest: None,
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+        run(&params, &repo).await.ok().unwrap();
+
+        // lib records what its enabled feature needs, and app records lib without the feature.
+        let installed = InstalledPackages::load(&repository_path).unwrap();
+        let dependencies = |name: &str| {
+            installed
+                .get(name)
+                .unwrap()
+                .dependencies
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(dependencies("app"), vec!["lib"]);
+        assert_eq!(dependencies("lib"), vec!["widgets"]);
+
+        let uninstall_params = |packages: &[&str]| uninstall::Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: packages.iter().map(|p| p.to_string()).collect(),
+            force: false,
+            remove_orphans: true,
+        };
+        let Err(error) = uninstall::run(&uninstall_params(&["widgets"])) else {
+            panic!("widgets is still needed by the gui feature of lib");
+        };
+        assert_eq!(error.base.to_string(), "widgets is still required by lib");
+
+        let result = uninstall::run(&uninstall_params(&["app"])).ok().unwrap();
+        let mut removed = result
+            .removed
+            .iter()
+            .map(|row| row.name.as_str())
+            .collect::<Vec<_>>();
+        removed.sort();
+        assert_eq!(removed, vec!["app", "lib", "widgets"]);
+    }
+
+    #[tokio::test]
+    async fn installs_overridden_package_from_local_path() {
+        let root = std::env::temp_dir().join("baryon-test-install-override");
+        let _ = std::fs::remove_dir_all(&root);
+        let repo = local_repository(&root.join("archives"));
+        let repository_path = root.join("repository");
+        let checkout = root.join("checkout");
+        std::fs::create_dir_all(&checkout).unwrap();
+        std::fs::write(checkout.join("package2.sc"), "Package2Local {}").unwrap();
+        let lock_path = root.join(LOCK_FILE);
+
+        // package1 1.2.0 needs package2 0.0.1, but the override forces 0.0.2 from the checkout.
+        let overrides = BTreeMap::from([(
+            "package2".to_string(),
+            PackageOverride {
+                version: Some("0.0.2".to_string()),
+                path: Some(checkout.to_string_lossy().to_string()),
+                ..Default::default()
+            },
+        )]);
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: vec![PackageSpec::parse("package1@=1.2.0")],
+            lockfile: Some(lock_path.to_string_lossy().to_string()),
+            locked: false,
+            manifest: None,
+            no_dev: false,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides,
+        };
+        let result = run(&params, &repo).await.ok().unwrap();
+        assert_eq!(
+            result.warnings,
+            vec![format!(
+                "package2 is overridden: version 0.0.2, path {}",
+                checkout.display()
+            )]
+        );
+        assert_eq!(
+            std::fs::read_to_string(repository_path.join("package2/package2.sc")).unwrap(),
+            "Package2Local {}"
+        );
+
+        let lock = Lockfile::load(&lock_path).unwrap().unwrap();
+        let package2 = lock.get("package2").unwrap();
+        assert_eq!(package2.version.to_string(), "0.0.2");
+        assert_eq!(package2.url, format!("file://{}", checkout.display()));
+        assert_eq!(package2.checksum, None);
+        assert_eq!(
+            package2.overridden.as_ref().and_then(|o| o.path.clone()),
+            Some(checkout.to_string_lossy().to_string())
+        );
+        assert_eq!(lock.get("package1").unwrap().overridden, None);
+    }
+}
+
+mod uninstall {
+    use baryon::actions::uninstall::{run, Parameters};
+    use baryon::core::installed::InstalledPackages;
+
+    const INSTALLED: &str = r#"{"packages": {
+        "a": {"name": "a", "version": "1.0.0", "url": "", "requirement": "*",
+              "dependencies": {"b": "1.0.0", "c": "1.0.0"}},
+        "b": {"name": "b", "version": "1.0.0", "url": "", "required_by": ["a"],
+              "dependencies": {"c": "1.0.0"}},
+        "c": {"name": "c", "version": "1.0.0", "url": "", "required_by": ["a", "b"]},
+        "d": {"name": "d", "version": "1.0.0", "url": "", "requirement": "*",
+              "dependencies": {"c": "1.0.0"}}
+    }}"#;
+
+    fn parameters(repository_path: &std::path::Path, packages: &[&str]) -> Parameters {
+        Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            packages: packages.iter().map(|p| p.to_string()).collect(),
+            force: false,
+            remove_orphans: true,
+        }
+    }
+
+    #[test]
+    fn removes_orphaned_dependencies() {
+        let repository_path = std::env::temp_dir().join("baryon-test-uninstall");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(repository_path.join("b")).unwrap();
+        std::fs::write(repository_path.join("installed.json"), INSTALLED).unwrap();
+
+        // b is still needed by a
+        assert!(run(&parameters(&repository_path, &["b"])).is_err());
+
+        let result = run(&parameters(&repository_path, &["a"])).ok().unwrap();
+        let removed = result
+            .removed
+            .iter()
+            .map(|row| (row.name.as_str(), row.orphan))
+            .collect::<Vec<_>>();
+        assert_eq!(removed, vec![("a", false), ("b", true)]);
+        assert!(!repository_path.join("b").exists());
+
+        let installed = InstalledPackages::load(&repository_path).unwrap();
+        let names = installed.packages.keys().collect::<Vec<_>>();
+        assert_eq!(names, vec!["c", "d"]);
+
+        let result = run(&parameters(&repository_path, &["d"])).ok().unwrap();
+        assert_eq!(result.removed.len(), 2);
+    }
+
+    #[test]
+    fn force_removes_required_package() {
+        let repository_path = std::env::temp_dir().join("baryon-test-uninstall-force");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(repository_path.join("installed.json"), INSTALLED).unwrap();
+
+        let mut params = parameters(&repository_path, &["c"]);
+        params.force = true;
+        let result = run(&params).ok().unwrap();
+        assert_eq!(result.removed.len(), 1);
+    }
+
+    #[test]
+    fn keeps_providers_of_required_packages() {
+        let repository_path = std::env::temp_dir().join("baryon-test-uninstall-providers");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        let installed = |rtmidi: bool| {
+            let rtmidi = match rtmidi {
+                true => {
+                    r#","rtmidi": {"name": "rtmidi", "version": "1.0.0", "url": "", "requirement": "*",
+                          "provides": {"midi-backend": "1.0.0"}}"#
+                }
+                false => "",
+            };
+            format!(
+                r#"{{"packages": {{
+                    "synth": {{"name": "synth", "version": "1.0.0", "url": "", "requirement": "*",
+                               "dependencies": {{"midi-backend": "^1.0"}}}},
+                    "portmidi": {{"name": "portmidi", "version": "1.0.0", "url": "", "required_by": ["synth"],
+                                  "provides": {{"midi-backend": "1.0.0"}}}}{}
+                }}}}"#,
+                rtmidi
+            )
+        };
+
+        // portmidi is the only midi-backend synth can use.
+        std::fs::write(repository_path.join("installed.json"), installed(false)).unwrap();
+        let Err(error) = run(&parameters(&repository_path, &["portmidi"])) else {
+            panic!("portmidi still provides midi-backend for synth");
+        };
+        assert_eq!(
+            error.base.to_string(),
+            "portmidi is still required by synth"
+        );
+
+        let result = run(&parameters(&repository_path, &["synth"])).ok().unwrap();
+        let removed = result
+            .removed
+            .iter()
+            .map(|row| (row.name.as_str(), row.orphan))
+            .collect::<Vec<_>>();
+        assert_eq!(removed, vec![("synth", false), ("portmidi", true)]);
+
+        // With rtmidi installed as well, portmidi can go.
+        std::fs::write(repository_path.join("installed.json"), installed(true)).unwrap();
+        let result = run(&parameters(&repository_path, &["portmidi"]))
+            .ok()
+            .unwrap();
+        assert_eq!(result.removed.len(), 1);
+    }
+}
+
+mod update {
+    use baryon::actions::update::{run, Change, Parameters};
+    use baryon::core::dependencies::Resolution;
+    use baryon::mocks::repository::MockRepository;
+    use std::collections::BTreeMap;
+
+    const INSTALLED: &str = r#"{"packages": {
+        "package1": {"name": "package1", "version": "1.0.0", "url": "", "requirement": "1.0.0"},
+        "package2": {"name": "package2", "version": "0.0.2", "url": "", "required_by": ["package1"]},
+        "package3": {"name": "package3", "version": "0.2.2", "url": "", "required_by": ["package1"]},
+        "stale": {"name": "stale", "version": "0.1.0", "url": ""}
+    }}"#;
+
+    #[tokio::test]
+    async fn dry_run_plans_changes() {
+        let repo = MockRepository::new().await;
+        let repository_path = std::env::temp_dir().join("baryon-test-update");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(repository_path.join("installed.json"), INSTALLED).unwrap();
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            dry_run: true,
+            lockfile: None,
+            manifest: None,
+            resolution: Resolution::Highest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+        let result = run(&params, &repo).await.ok().unwrap();
+
+        assert!(!result.applied);
+        assert_eq!(
+            result.plan.changes,
+            vec![
+                Change::Upgrade {
+                    name: "package1".to_string(),
+                    from: "1.0.0".to_string(),
+                    to: "1.2.0".to_string()
+                },
+                Change::Downgrade {
+                    name: "package2".to_string(),
+                    from: "0.0.2".to_string(),
+                    to: "0.0.1".to_string()
+                },
+                Change::Remove {
+                    name: "stale".to_string(),
+                    version: "0.1.0".to_string()
+                },
+            ]
+        );
+
+        let json = serde_json::to_value(&result.plan).unwrap();
+        assert_eq!(json["changes"][0]["action"], "upgrade");
+
+        // Nothing on disk was touched.
+        let on_disk = std::fs::read_to_string(repository_path.join("installed.json")).unwrap();
+        assert_eq!(on_disk, INSTALLED);
+    }
+
+    #[tokio::test]
+    async fn dry_run_follows_resolution() {
+        let repo = MockRepository::new().await;
+        let repository_path = std::env::temp_dir().join("baryon-test-update-lowest");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(repository_path.join("installed.json"), INSTALLED).unwrap();
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            dry_run: true,
+            lockfile: None,
+            manifest: None,
+            resolution: Resolution::Lowest,
+            max_steps: None,
+            timeout_seconds: None,
+            strict: false,
+            target: None,
+            supercollider: None,
+            overrides: BTreeMap::new(),
+        };
+        let result = run(&params, &repo).await.ok().unwrap();
+        let _ = std::fs::remove_dir_all(&repository_path);
+
+        assert!(!result
+            .plan
+            .changes
+            .iter()
+            .any(|change| change.name() == "package1"));
+    }
+}
+
+mod outdated {
+    use baryon::actions::outdated::{run, Parameters};
+    use baryon::mocks::repository::MockRepository;
+
+    #[tokio::test]
+    async fn splits_compatible_and_breaking_updates() {
+        let repo = MockRepository::new().await;
+        let repository_path = std::env::temp_dir().join("baryon-test-outdated");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(
+            repository_path.join("installed.json"),
+            r#"{"packages": {
+                "package1": {"name": "package1", "version": "1.0.0", "url": "", "requirement": "^1.0.0"},
+                "package3": {"name": "package3", "version": "0.2.2", "url": "", "requirement": "~0.2"}
+            }}"#,
+        )
+        .unwrap();
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            include_prerelease: false,
+            strict: false,
+        };
+        let result = run(&params, &repo).ok().unwrap();
+        assert_eq!(result.packages.len(), 2);
+
+        let package1 = &result.packages[0];
+        assert_eq!(package1.compatible.as_deref(), Some("1.2.0"));
+        assert_eq!(package1.breaking, None);
+
+        let package3 = &result.packages[1];
+        assert_eq!(package3.compatible, None);
+        assert_eq!(package3.breaking.as_deref(), Some("0.3.1"));
+        let _ = std::fs::remove_dir_all(&repository_path);
+    }
+
+    #[tokio::test]
+    async fn flags_yanked_releases() {
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml")
+            .unwrap()
+            .replace(
+                "    - version: 1.2.0\n",
+                "    - version: 1.2.0\n      yanked: true\n",
+            )
+            .replace(
+                "    - version: 0.2.2\n",
+                "    - version: 0.2.2\n      yanked: true\n      yanked_reason: broken build\n",
+            );
+        let repo = MockRepository::from_yaml(&yaml);
+        let repository_path = std::env::temp_dir().join("baryon-test-outdated-yanked");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(
+            repository_path.join("installed.json"),
+            r#"{"packages": {
+                "package1": {"name": "package1", "version": "1.0.0", "url": "", "requirement": "^1.0.0"},
+                "package3": {"name": "package3", "version": "0.2.2", "url": "", "requirement": "*"}
+            }}"#,
+        )
+        .unwrap();
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            include_prerelease: false,
+            strict: false,
+        };
+        let result = run(&params, &repo).ok().unwrap();
+        assert_eq!(result.packages.len(), 1);
+
+        // package1 1.2.0 is yanked, so it is not offered; package3 0.2.2 is installed but yanked.
+        let package3 = &result.packages[0];
+        assert_eq!(package3.name, "package3");
+        assert!(package3.yanked);
+        assert_eq!(package3.yanked_reason.as_deref(), Some("broken build"));
+        assert_eq!(package3.compatible.as_deref(), Some("0.3.1"));
+        let _ = std::fs::remove_dir_all(&repository_path);
+    }
+
+    #[tokio::test]
+    async fn counts_feature_requirements_as_constraints() {
+        let repo = MockRepository::new().await;
+        let repository_path = std::env::temp_dir().join("baryon-test-outdated-features");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(
+            repository_path.join("installed.json"),
+            r#"{"packages": {
+                "app": {"name": "app", "version": "1.0.0", "url": "", "requirement": "*",
+                        "dependencies": {"package3[gui]": "~0.2"}},
+                "package3": {"name": "package3", "version": "0.2.2", "url": "", "required_by": ["app"]}
+            }}"#,
+        )
+        .unwrap();
+
+        let params = Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            include_prerelease: false,
+            strict: false,
+        };
+        let result = run(&params, &repo).ok().unwrap();
+
+        // app holds package3 back, though it asks for a feature of it.
+        let package3 = &result.packages[0];
+        assert_eq!(package3.requirements, vec!["~0.2"]);
+        assert_eq!(package3.compatible, None);
+        assert_eq!(package3.breaking.as_deref(), Some("0.3.1"));
+        let _ = std::fs::remove_dir_all(&repository_path);
+    }
+}
+
+mod search {
+    use baryon::actions::search::{run, Field, Parameters};
+    use baryon::mocks::repository::MockRepository;
+
+    fn search(repo: &MockRepository, terms: &[&str]) -> Vec<(String, Vec<Field>)> {
+        let params = Parameters {
+            repository_url: String::new(),
+            terms: terms.iter().map(|t| t.to_string()).collect(),
+            limit: None,
+        };
+        run(&params, repo)
+            .ok()
+            .unwrap()
+            .packages
+            .into_iter()
+            .map(|row| (row.name, row.matched))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ranks_name_matches_first() {
+        let repo = MockRepository::new().await;
+
+        let results = search(&repo, &["package2"]);
+        assert_eq!(results[0].0, "package2");
+        assert!(results[0].1.contains(&Field::Name));
+    }
+
+    #[tokio::test]
+    async fn matches_descriptions_authors_and_typos() {
+        let repo = MockRepository::new().await;
+
+        assert_eq!(search(&repo, &["test"]).len(), 3);
+        assert_eq!(search(&repo, &["person"])[0].1, vec![Field::Authors]);
+        assert_eq!(search(&repo, &["pakcage3"])[0].0, "package3");
+        assert!(search(&repo, &["nothing"]).is_empty());
+    }
+}
+
+mod info {
+    use baryon::actions::info::{run, Parameters};
+    use baryon::mocks::repository::MockRepository;
+
+    const BROKEN: &str = r#"
+package4:
+  name: package4
+  description: Depends on something that does not exist.
+  authors: [person]
+  license: MIT
+  url: https://homepage.org/package4
+  repo: https://github.com/person/package4
+  releases:
+    - version: 0.1.0
+      url: https://homepage.org/package4/versions/0.1.0
+    - version: 0.2.0
+      url: https://homepage.org/package4/versions/0.2.0
+      yanked: true
+      dependencies:
+        package3: ^0.2
+    - version: 0.3.0
+      url: https://homepage.org/package4/versions/0.3.0
+      dependencies:
+        package1: =1.0.0
+        package3: ^0.3
+    - version: 0.4.0
+      url: https://homepage.org/package4/versions/0.4.0
+      dependencies:
+        missing:
+          version: 1.0.0
+          os: [plan9]
+    - version: 0.10.0
+      url: https://homepage.org/package4/versions/0.10.0
+      dependencies:
+        missing: 1.0.0
+"#;
+
+    #[tokio::test]
+    async fn describes_releases() {
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml").unwrap();
+        let repo = MockRepository::from_yaml(&format!("{}{}", yaml, BROKEN));
+        let repository_path = std::env::temp_dir().join("baryon-test-info");
+        let _ = std::fs::remove_dir_all(&repository_path);
+        std::fs::create_dir_all(&repository_path).unwrap();
+        std::fs::write(
+            repository_path.join("installed.json"),
+            r#"{"packages": {"package4": {"name": "package4", "version": "0.1.0", "url": ""}}}"#,
+        )
+        .unwrap();
+
+        let params = |target: Option<&str>| Parameters {
+            repository_path: repository_path.to_string_lossy().to_string(),
+            repository_url: String::new(),
+            package: "package4".to_string(),
+            strict: false,
+            max_steps: None,
+            timeout_seconds: None,
+            target: target.map(|target| target.parse().unwrap()),
+            supercollider: None,
+        };
+        let result = run(&params(Some("linux-x86_64")), &repo).ok().unwrap();
+
+        assert_eq!(result.installed_version.as_deref(), Some("0.1.0"));
+        let versions = result
+            .releases
+            .iter()
+            .map(|r| (r.version.as_str(), r.installed, r.installable, r.yanked))
+            .collect::<Vec<_>>();
+        // Yanked releases can still be installed from a lockfile, while 0.3.0 only conflicts
+        // further down its dependencies.
+        assert_eq!(
+            versions,
+            vec![
+                ("0.10.0", false, false, false),
+                ("0.4.0", false, true, false),
+                ("0.3.0", false, false, false),
+                ("0.2.0", false, true, true),
+                ("0.1.0", true, true, false)
+            ]
+        );
+        let plan9 = run(&params(Some("plan9-x86_64")), &repo).ok().unwrap();
+        assert!(!plan9.releases[1].installable);
+        assert_eq!(result.releases[0].dependencies["missing"], "1.0.0");
+        let _ = std::fs::remove_dir_all(&repository_path);
+    }
+}
+
+mod config {
+    use baryon::core::config::{set_value, Config, ConfigSource};
+    use std::collections::HashMap;
+
+    #[test]
+    fn merges_layers_in_order() {
+        let root = std::env::temp_dir().join("baryon-test-config");
+        let _ = std::fs::remove_dir_all(&root);
+        let user = root.join("user/config.yaml");
+        let project = root.join("project/config.yaml");
+
+        set_value(&user, "repository_url", "https://user.example/repo.json").unwrap();
+        set_value(&user, "cache_path", "$CACHE_ROOT/baryon").unwrap();
+        set_value(
+            &project,
+            "repository_url",
+            "https://project.example/repo.json",
+        )
+        .unwrap();
+        assert!(set_value(&project, "not_a_key", "value").is_err());
+
+        let env = HashMap::from([
+            ("CACHE_ROOT".to_string(), "/tmp/cache".to_string()),
+            ("BARYON_CACHE_TIMEOUT".to_string(), "120".to_string()),
+        ]);
+        let overrides = vec![("global_repository_path".to_string(), "~/quarks".to_string())];
+        let config = Config::from_sources(Some(&user), Some(&project), env, &overrides).unwrap();
+
+        let url = config.get("repository_url").unwrap();
+        assert_eq!(url.value, "https://project.example/repo.json");
+        assert_eq!(url.source, ConfigSource::Project);
+        assert_eq!(config.get("cache_path").unwrap().source, ConfigSource::User);
+        assert_eq!(
+            config.get("cache_timeout").unwrap().origin,
+            "BARYON_CACHE_TIMEOUT"
+        );
+        assert_eq!(
+            config.get("global_repository_path").unwrap().source,
+            ConfigSource::CommandLine
+        );
+
+        let settings = config.settings().unwrap();
+        assert_eq!(settings.cache_settings.cache_path, "/tmp/cache/baryon");
+        assert_eq!(settings.cache_settings.cache_timeout.as_secs(), 120);
+        assert!(!settings.global_repository_path.starts_with('~'));
+        assert!(settings.global_repository_path.ends_with("quarks"));
+    }
+
+    #[test]
+    fn refuses_values_settings_cannot_read() {
+        let root = std::env::temp_dir().join("baryon-test-config-invalid");
+        let _ = std::fs::remove_dir_all(&root);
+        let user = root.join("config.yaml");
+
+        for (key, value) in [
+            ("cache_timeout", "abc"),
+            ("strict_repository", "yes"),
+            ("pins", "[package1]"),
+            ("supercollider_version", "three"),
+        ] {
+            let error = set_value(&user, key, value).err().unwrap();
+            assert!(error.to_string().contains(key), "{}", error);
+        }
+        assert!(!user.exists());
+
+        set_value(&user, "cache_timeout", "30").unwrap();
+        set_value(&user, "supercollider_version", "").unwrap();
+        let config = Config::from_sources(Some(&user), None, HashMap::new(), &[]).unwrap();
+        assert_eq!(
+            config
+                .settings()
+                .unwrap()
+                .cache_settings
+                .cache_timeout
+                .as_secs(),
+            30
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
+
+mod repositories {
+    use baryon::actions::repo::{run, Parameters};
+    use baryon::core::config::Config;
+    use baryon::core::repository::{CompositeRepository, NamedRepository, Repository};
+    use baryon::mocks::repository::MockRepository;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn named(name: &str, priority: i64, description: &str) -> NamedRepository {
+        let yaml = std::fs::read_to_string("src/mocks/repository.yaml").unwrap();
+        let yaml = yaml.replace("This is a test package.", description);
+        NamedRepository {
+            name: name.to_string(),
+            priority,
+            repository: Box::new(MockRepository::from_yaml(&yaml)),
+        }
+    }
+
+    #[test]
+    fn merges_by_priority_and_pins() {
+        let pins = BTreeMap::from([("package3".to_string(), "public".to_string())]);
+        let repo = CompositeRepository::new(
+            vec![
+                named("public", 0, "public"),
+                named("private", 10, "private"),
+                named("mirror", 10, "mirror"),
+            ],
+            &pins,
+        );
+
+        assert_eq!(repo.get_packages().len(), 3);
+        // Highest priority wins, ties go to the repository configured first.
+        assert_eq!(repo.get_package("package1").unwrap().description, "private");
+        assert_eq!(repo.source_of("package1"), Some("private"));
+        // Pinned packages ignore priority.
+        assert_eq!(repo.get_package("package3").unwrap().description, "public");
+        assert_eq!(repo.source_of("package3"), Some("public"));
+    }
+
+    #[test]
+    fn adds_and_removes_repositories() {
+        let root = std::env::temp_dir().join("baryon-test-repo");
+        let _ = std::fs::remove_dir_all(&root);
+        let user = root.join("config.yaml");
+        let config = Config::from_sources(Some(&user), None, HashMap::new(), &[]).unwrap();
+
+        let add = Parameters::Add {
+            name: "private".to_string(),
+            url: "https://private.example/repo.json".to_string(),
+            priority: 10,
+            token: Some("${PRIVATE_TOKEN}".to_string()),
+            project: false,
+        };
+        let result = run(&add, &config).ok().unwrap();
+        let names = result
+            .repositories
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["private", "default"]);
+        assert!(result.repositories[0].authenticated);
+        // The token is stored unexpanded.
+        assert!(std::fs::read_to_string(&user)
+            .unwrap()
+            .contains("${PRIVATE_TOKEN}"));
+
+        let config = config.reload().unwrap();
+        let remove = Parameters::Remove {
+            name: "default".to_string(),
+            project: false,
+        };
+        let result = run(&remove, &config).ok().unwrap();
+        assert_eq!(result.repositories.len(), 1);
+
+        let missing = Parameters::Remove {
+            name: "default".to_string(),
+            project: false,
+        };
+        assert!(run(&missing, &config.reload().unwrap()).is_err());
+    }
+
+    #[test]
+    fn edits_only_the_chosen_file() {
+        let root = std::env::temp_dir().join("baryon-test-repo-layers");
+        let _ = std::fs::remove_dir_all(&root);
+        let user = root.join("config.yaml");
+        let env = HashMap::from([(
+            "BARYON_REPOSITORIES".to_string(),
+            "[{name: ci, url: https://ci.example/repo.json}]".to_string(),
+        )]);
+        let config = Config::from_sources(Some(&user), None, env, &[]).unwrap();
+
+        let add = |name: &str| Parameters::Add {
+            name: name.to_string(),
+            url: "https://private.example/repo.json".to_string(),
+            priority: 10,
+            token: None,
+            project: false,
+        };
+        assert!(run(&add("../escape"), &config).is_err());
+        run(&add("private"), &config).ok().unwrap();
+        // The environment's repository stays out of the user file.
+        let written = std::fs::read_to_string(&user).unwrap();
+        assert!(written.contains("private") && written.contains("default"));
+        assert!(!written.contains("ci.example"), "{}", written);
+
+        std::fs::write(
+            &user,
+            written + "pins:\n  package1: private\n  package2: default\n",
+        )
+        .unwrap();
+        let remove = Parameters::Remove {
+            name: "private".to_string(),
+            project: false,
+        };
+        let result = run(&remove, &config.reload().unwrap()).ok().unwrap();
+        // Pins to the removed repository go with it.
+        assert_eq!(
+            result.pins,
+            BTreeMap::from([("package2".to_string(), "default".to_string())])
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn pins_only_configured_repositories() {
+        let root = std::env::temp_dir().join("baryon-test-pin");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let user = root.join("config.yaml");
+        let config = Config::from_sources(Some(&user), None, HashMap::new(), &[]).unwrap();
+        let pin = |repository: &str| Parameters::Pin {
+            package: "package1".to_string(),
+            repository: repository.to_string(),
+            project: false,
+        };
+
+        let error = run(&pin("typo"), &config).err().unwrap();
+        assert!(error.base.to_string().contains("typo"), "{}", error.base);
+        let result = run(&pin("default"), &config).ok().unwrap();
+        assert_eq!(result.pins["package1"], "default");
+
+        // A pins section that does not parse is reported, not overwritten.
+        std::fs::write(&user, "pins: [package1]\n").unwrap();
+        let unpin = Parameters::Unpin {
+            package: "package1".to_string(),
+            project: false,
+        };
+        let error = run(&unpin, &config.reload().unwrap()).err().unwrap();
+        assert!(error.base.to_string().contains("pins"), "{}", error.base);
+        assert_eq!(
+            std::fs::read_to_string(&user).unwrap(),
+            "pins: [package1]\n"
+        );
+    }
+}
+
+mod manifest {
+    use baryon::core::dependencies::ResolveLimits;
+    use baryon::core::http::CacheSettings;
+    use baryon::core::manifest::{apply_repositories, load_into, requirements, MANIFEST_FILE};
+    use baryon::core::settings::{PackageOverride, Settings};
+    use baryon::specs::manifest::Manifest;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    const MANIFEST: &str = r#"
+name: project
+dependencies:
+  package1: ^1.0.0
+  package2: "*"
+dev-dependencies:
+  package2: =0.0.1
+  package3: ^0.2.0
+repositories:
+  - name: private
+    url: https://private.example/repo.json
+    priority: 10
+    auth:
+      token: ${PRIVATE_TOKEN}
+  - name: mirror
+    url: https://mirror.example/repo.json
+"#;
+
+    #[test]
+    fn lists_requirements() {
+        let manifest: Manifest = serde_yaml::from_str(MANIFEST).unwrap();
+        assert_eq!(manifest.name, "project");
+
+        let regular = requirements(&manifest, false);
+        assert_eq!(
+            regular.keys().collect::<Vec<_>>(),
+            vec!["package1", "package2"]
+        );
+
+        // Regular dependencies win over dev-dependencies on the same package.
+        let all = requirements(&manifest, true);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all["package2"], "*");
+    }
+
+    #[test]
+    fn adds_repositories_to_settings() {
+        let manifest: Manifest = serde_yaml::from_str(MANIFEST).unwrap();
+        let mut settings = Settings {
+            global_repository_path: String::new(),
+            repository_url: "https://example.com/repo.json".to_string(),
+            repositories: vec![],
+            pins: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            cache_settings: CacheSettings {
+                cache_path: String::new(),
+                cache_timeout: Duration::from_secs(60),
+            },
+            resolve_limits: ResolveLimits::default(),
+            strict_repository: false,
+            expand_manifest_tokens: false,
+            supercollider_version: None,
+        };
+
+        apply_repositories(&manifest, &mut settings).unwrap();
+        let sources = settings
+            .repositories
+            .iter()
+            .map(|s| (s.name.as_str(), s.url.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                ("default", "https://example.com/repo.json"),
+                ("private", "https://private.example/repo.json"),
+                ("mirror", "https://mirror.example/repo.json"),
+            ]
+        );
+        // The manifest picks where tokens go, so they are not filled in from the environment.
+        assert_eq!(
+            settings.repositories[1].auth.as_ref().unwrap().token,
+            "${PRIVATE_TOKEN}"
+        );
+
+        // Nor can it take over a configured repository.
+        settings.repositories.clear();
+        let taken: Manifest =
+            serde_yaml::from_str(&MANIFEST.replace("name: mirror", "name: default")).unwrap();
+        assert!(apply_repositories(&taken, &mut settings).is_err());
+    }
+
+    #[test]
+    fn takes_override_paths_from_the_manifest_directory() {
+        let project = std::env::temp_dir().join("baryon-test-manifest-overrides");
+        std::fs::create_dir_all(&project).unwrap();
+        let manifest_path = project.join(MANIFEST_FILE);
+        std::fs::write(
+            &manifest_path,
+            "name: project\noverrides:\n  package1:\n    path: ../package1\n  package2:\n    version: 0.0.1\n",
+        )
+        .unwrap();
+        let mut settings = Settings {
+            global_repository_path: String::new(),
+            repository_url: "https://example.com/repo.json".to_string(),
+            repositories: vec![],
+            pins: BTreeMap::new(),
+            overrides: BTreeMap::from([(
+                "package2".to_string(),
+                PackageOverride {
+                    path: Some("elsewhere".to_string()),
+                    ..Default::default()
+                },
+            )]),
+            cache_settings: CacheSettings {
+                cache_path: String::new(),
+                cache_timeout: Duration::from_secs(60),
+            },
+            resolve_limits: ResolveLimits::default(),
+            strict_repository: false,
+            expand_manifest_tokens: false,
+            supercollider_version: None,
+        };
+
+        assert!(load_into(&manifest_path, &mut settings).unwrap().is_some());
+        assert_eq!(
+            settings.overrides["package1"].path.as_deref(),
+            Some(project.join("../package1").to_string_lossy().as_ref())
+        );
+        // The manifest's override replaces the configured one.
+        assert_eq!(
+            settings.overrides["package2"],
+            PackageOverride {
+                version: Some("0.0.1".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+}
+
+mod version {
+    use baryon::core::version::{parse_version, VersionSpec};
+    use semver::Version;
+
+    fn matching(spec: &str, versions: &[&str]) -> Vec<String> {
+        let spec = VersionSpec::parse(spec).unwrap();
+        versions
+            .iter()
+            .filter(|v| spec.matches(&Version::parse(v).unwrap()))
+            .map(|v| v.to_string())
+            .collect()
+    }
+
+    const VERSIONS: &[&str] = &[
+        "1.1.0", "1.2.0", "1.4.9", "1.5.0", "2.0.0", "2.3.1", "3.0.0",
+    ];
+
+    #[test]
+    fn matches_any_alternative() {
+        assert_eq!(
+            matching("^1.2 || ^2.0", VERSIONS),
+            vec!["1.2.0", "1.4.9", "1.5.0", "2.0.0", "2.3.1"]
+        );
+        assert_eq!(
+            matching("=1.1.0 || >=3.0.0", VERSIONS),
+            vec!["1.1.0", "3.0.0"]
+        );
+    }
+
+    #[test]
+    fn desugars_hyphen_ranges() {
+        assert_eq!(matching("1.2.0 - 1.4.x", VERSIONS), vec!["1.2.0", "1.4.9"]);
+        assert_eq!(
+            matching("1.2.0 - 2.0.0", VERSIONS),
+            vec!["1.2.0", "1.4.9", "1.5.0", "2.0.0"]
+        );
+        assert_eq!(
+            matching("1.5 - 2", VERSIONS),
+            vec!["1.5.0", "2.0.0", "2.3.1"]
+        );
+        assert_eq!(
+            VersionSpec::parse("1.2.0 - 1.4.x").unwrap().to_string(),
+            ">=1.2.0, <1.5.0"
+        );
+    }
+
+    #[test]
+    fn reads_legacy_versions() {
+        for (raw, expected) in [
+            ("1.2.3", "1.2.3"),
+            ("1.2", "1.2.0"),
+            ("3", "3.0.0"),
+            ("v0.3", "0.3.0"),
+            (" V1.0.1 ", "1.0.1"),
+            ("01.02", "1.2.0"),
+            ("1.0beta", "1.0.0-beta"),
+            ("2.1b2", "2.1.0-b.2"),
+            ("1.0.beta", "1.0.0-beta"),
+            ("1.0-rc.1", "1.0.0-rc.1"),
+            ("1.0+build.5", "1.0.0+build.5"),
+        ] {
+            assert_eq!(parse_version(raw).unwrap().to_string(), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn rejects_ambiguous_versions() {
+        for raw in ["1.2.3.4", "beta", "1..2", "1.0 beta", "1.0_beta"] {
+            assert!(parse_version(raw).is_err(), "{}", raw);
+        }
+        let error = parse_version("1.2.3.4").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot read version \"1.2.3.4\": it has more than three numbers"
+        );
+    }
+
+    #[test]
+    fn accepts_space_separated_comparators() {
+        assert_eq!(
+            matching(">=1.2.0 <2", VERSIONS),
+            vec!["1.2.0", "1.4.9", "1.5.0"]
+        );
+        assert_eq!(
+            matching(">= 2.0.0, < 3.0.0", VERSIONS),
+            vec!["2.0.0", "2.3.1"]
+        );
+        assert!(VersionSpec::parse("^1.0 ||").is_err());
+    }
 }